@@ -0,0 +1,78 @@
+// Compares decoding to UTF-8 a char at a time with `decode_utf8_into`, on
+// ASCII identifiers and on strings from the other pages. Run with
+// `cargo bench`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use sixbit::{encode, DecodeSixbit, PackedValue};
+
+const IDENTS: [&str; 12] = [
+    "x",
+    "id",
+    "name",
+    "user_id",
+    "NO_CARRIER",
+    "created_at",
+    "PacketSize",
+    "x86_64",
+    "Printer_Working",
+    "PRINTER_is_on_FIRE",
+    "max_retry_count",
+    "HttpRequest2",
+];
+
+const OTHERS: [&str; 3] = ["αλήθεια", "合伙人", "ｲｸﾂｶﾉ"];
+
+const ROUNDS: usize = 200_000;
+
+// Nanoseconds per value, the best of a few runs.
+fn time<N: PackedValue>(packed: &[N], mut decode: impl FnMut(N) -> usize) -> f64 {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for &n in packed {
+                    black_box(decode(black_box(n)));
+                }
+            }
+            start.elapsed().as_nanos() as f64 / (ROUNDS * packed.len()) as f64
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn bench<N: PackedValue>(name: &str, words: &[&str]) {
+    let packed: Vec<N> = words
+        .iter()
+        .filter_map(|w| encode(w.chars()).ok())
+        .collect();
+    let mut buf = [0u8; 128];
+
+    let chars = time(&packed, |n| {
+        let mut len = 0;
+        for c in n.decode_sixbit() {
+            len += c.encode_utf8(&mut buf[len..]).len();
+        }
+        black_box(&buf[..len]);
+        len
+    });
+    let bytes = time(&packed, |n| {
+        let len = n.decode_utf8_into(&mut buf);
+        black_box(&buf[..len]);
+        len
+    });
+
+    println!(
+        "{:<14} chars {:6.1} ns  decode_utf8_into {:6.1} ns  {:4.1}x",
+        name,
+        chars,
+        bytes,
+        chars / bytes
+    );
+}
+
+fn main() {
+    bench::<u64>("u64 idents", &IDENTS[..8]);
+    bench::<u128>("u128 idents", &IDENTS);
+    bench::<u64>("u64 others", &OTHERS);
+}
@@ -0,0 +1,61 @@
+// Compares encoding from chars with encoding from UTF-8 bytes, on ASCII
+// identifiers and on strings that take the general path. Run with
+// `cargo bench`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use sixbit::{encode, encode_utf8, PackedValue};
+
+// The first 8 fit in a u64.
+const IDENTS: [&str; 12] = [
+    "x",
+    "id",
+    "name",
+    "user_id",
+    "NO_CARRIER",
+    "created_at",
+    "PacketSize",
+    "x86_64",
+    "Printer_Working",
+    "PRINTER_is_on_FIRE",
+    "max_retry_count",
+    "HttpRequest2",
+];
+
+const OTHERS: [&str; 4] = ["αλήθεια", "合伙人", "ｲｸﾂｶﾉ", "sh@rk"];
+
+const ROUNDS: usize = 200_000;
+
+fn bench<N: PackedValue>(name: &str, words: &[&str]) {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for w in words {
+            black_box(encode::<N, _>(black_box(w).chars()).ok());
+        }
+    }
+    let chars = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for w in words {
+            black_box(encode_utf8::<N>(black_box(w).as_bytes()).ok());
+        }
+    }
+    let bytes = start.elapsed();
+
+    let per = |d: std::time::Duration| d.as_nanos() as f64 / (ROUNDS * words.len()) as f64;
+    println!(
+        "{:<14} encode {:6.1} ns  encode_utf8 {:6.1} ns  {:4.1}x",
+        name,
+        per(chars),
+        per(bytes),
+        chars.as_secs_f64() / bytes.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench::<u64>("u64 idents", &IDENTS[..8]);
+    bench::<u128>("u128 idents", &IDENTS);
+    bench::<u64>("u64 others", &OTHERS);
+}
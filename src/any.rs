@@ -0,0 +1,408 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use crate::{
+    chinese_15bit_delta, code_in_page, lookup, DecodeSixbitIter, EncodeError, PackedValue, Page,
+    Sixbit, Sixbit128, Sixbit16, Sixbit32, Sixbit64, Sixbit8, SixbitStr, CHINESE_4BIT_TAG,
+};
+
+// A packed string at whichever width it fit in. `encode_smallest` picks the
+// narrowest one, but any of these can hold any string that fits, so equality,
+// ordering and hashing all go by the string rather than the variant: a
+// `Sixbit64` holding "a" is equal to a `Sixbit8` holding "a", and sorts
+// before a `Sixbit8` holding "b".
+#[derive(Clone, Copy)]
+pub enum SixbitAny {
+    Sixbit8(Sixbit8),
+    Sixbit16(Sixbit16),
+    Sixbit32(Sixbit32),
+    Sixbit64(Sixbit64),
+    Sixbit128(Sixbit128),
+}
+
+pub enum SixbitAnyChars {
+    Sixbit8(DecodeSixbitIter<u8>),
+    Sixbit16(DecodeSixbitIter<u16>),
+    Sixbit32(DecodeSixbitIter<u32>),
+    Sixbit64(DecodeSixbitIter<u64>),
+    Sixbit128(DecodeSixbitIter<u128>),
+}
+
+macro_rules! each_width {
+    ($e:expr, $v:ident => $body:expr) => {
+        match $e {
+            SixbitAny::Sixbit8($v) => $body,
+            SixbitAny::Sixbit16($v) => $body,
+            SixbitAny::Sixbit32($v) => $body,
+            SixbitAny::Sixbit64($v) => $body,
+            SixbitAny::Sixbit128($v) => $body,
+        }
+    };
+}
+
+macro_rules! each_iter {
+    ($e:expr, $v:ident => $body:expr) => {
+        match $e {
+            SixbitAnyChars::Sixbit8($v) => $body,
+            SixbitAnyChars::Sixbit16($v) => $body,
+            SixbitAnyChars::Sixbit32($v) => $body,
+            SixbitAnyChars::Sixbit64($v) => $body,
+            SixbitAnyChars::Sixbit128($v) => $body,
+        }
+    };
+}
+
+// Whether a string of `len` characters from page `tag` (or Chinese, if
+// `wide`) fits in an `N`.
+fn fits<N: PackedValue>(tag: usize, wide: bool, len: usize) -> bool {
+    if wide {
+        len <= N::NWIDECHARS
+    } else {
+        (N::NTAGBITS == 4 || tag & 0b11 == 0) && len <= N::NCHARS
+    }
+}
+
+// Encode in the narrowest width with room for the string's page and length.
+// The string is scanned once to find both, and then encoded once at the
+// chosen width, rather than trying each width in turn until one works.
+pub fn encode_smallest(s: &str) -> Result<SixbitAny, EncodeError> {
+    let init = match s.chars().next() {
+        // Zero-length strings map to page 0, code 0.
+        None => return Ok(SixbitAny::Sixbit8(Sixbit(0))),
+        Some(c) => c,
+    };
+    let wide = chinese_15bit_delta(init).is_some();
+    let tag = if wide {
+        CHINESE_4BIT_TAG
+    } else {
+        match lookup(init).map(|(p, _)| p) {
+            None => {
+                return Err(EncodeError::NoCodePageFor {
+                    position: 0,
+                    c: init,
+                })
+            }
+            Some(p) => p,
+        }
+    };
+    let mut len = 0;
+    for c in s.chars() {
+        let found = if wide {
+            chinese_15bit_delta(c).is_some()
+        } else {
+            code_in_page(tag, c).is_some()
+        };
+        if !found {
+            return Err(EncodeError::MissingFromPage {
+                position: len,
+                c,
+                page: Page::from_index(tag),
+            });
+        }
+        len += 1;
+    }
+    if fits::<u8>(tag, wide, len) {
+        Sixbit::encode(s.chars()).map(SixbitAny::Sixbit8)
+    } else if fits::<u16>(tag, wide, len) {
+        Sixbit::encode(s.chars()).map(SixbitAny::Sixbit16)
+    } else if fits::<u32>(tag, wide, len) {
+        Sixbit::encode(s.chars()).map(SixbitAny::Sixbit32)
+    } else if fits::<u64>(tag, wide, len) {
+        Sixbit::encode(s.chars()).map(SixbitAny::Sixbit64)
+    } else if fits::<u128>(tag, wide, len) {
+        Sixbit::encode(s.chars()).map(SixbitAny::Sixbit128)
+    } else {
+        // Room in the widest width that has the page.
+        let available = if wide {
+            u128::NWIDECHARS
+        } else if tag & 0b11 == 0 {
+            u128::NCHARS
+        } else {
+            u64::NCHARS
+        };
+        Err(EncodeError::too_long(available, tag, len, available))
+    }
+}
+
+impl SixbitAny {
+    // The width of the packed value, in bits.
+    pub fn width(self) -> usize {
+        match self {
+            SixbitAny::Sixbit8(_) => 8,
+            SixbitAny::Sixbit16(_) => 16,
+            SixbitAny::Sixbit32(_) => 32,
+            SixbitAny::Sixbit64(_) => 64,
+            SixbitAny::Sixbit128(_) => 128,
+        }
+    }
+
+    pub fn char_len(self) -> usize {
+        each_width!(self, s => s.get().char_len())
+    }
+
+    pub fn chars(self) -> SixbitAnyChars {
+        match self {
+            SixbitAny::Sixbit8(s) => SixbitAnyChars::Sixbit8(s.chars()),
+            SixbitAny::Sixbit16(s) => SixbitAnyChars::Sixbit16(s.chars()),
+            SixbitAny::Sixbit32(s) => SixbitAnyChars::Sixbit32(s.chars()),
+            SixbitAny::Sixbit64(s) => SixbitAnyChars::Sixbit64(s.chars()),
+            SixbitAny::Sixbit128(s) => SixbitAnyChars::Sixbit128(s.chars()),
+        }
+    }
+}
+
+impl Iterator for SixbitAnyChars {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        each_iter!(self, it => it.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        each_iter!(self, it => it.size_hint())
+    }
+}
+
+impl DoubleEndedIterator for SixbitAnyChars {
+    fn next_back(&mut self) -> Option<char> {
+        each_iter!(self, it => it.next_back())
+    }
+}
+
+impl ExactSizeIterator for SixbitAnyChars {}
+
+impl PartialEq for SixbitAny {
+    fn eq(&self, other: &SixbitAny) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SixbitAny {}
+
+impl PartialOrd for SixbitAny {
+    fn partial_cmp(&self, other: &SixbitAny) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SixbitAny {
+    fn cmp(&self, other: &SixbitAny) -> Ordering {
+        // Values of the same width compare as integers, which matches string
+        // order; otherwise there's nothing for it but to decode both.
+        match (self, other) {
+            (SixbitAny::Sixbit8(a), SixbitAny::Sixbit8(b)) => a.cmp(b),
+            (SixbitAny::Sixbit16(a), SixbitAny::Sixbit16(b)) => a.cmp(b),
+            (SixbitAny::Sixbit32(a), SixbitAny::Sixbit32(b)) => a.cmp(b),
+            (SixbitAny::Sixbit64(a), SixbitAny::Sixbit64(b)) => a.cmp(b),
+            (SixbitAny::Sixbit128(a), SixbitAny::Sixbit128(b)) => a.cmp(b),
+            _ => self.chars().cmp(other.chars()),
+        }
+    }
+}
+
+impl Hash for SixbitAny {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must agree with `eq`, so hash the string, not the variant, and the
+        // same way `str` does, so that it's terminated.
+        each_width!(self, s => SixbitStr::from(s.get()).as_str().hash(state))
+    }
+}
+
+impl fmt::Display for SixbitAny {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        each_width!(self, s => fmt::Display::fmt(s, f))
+    }
+}
+
+impl fmt::Debug for SixbitAny {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        each_width!(self, s => fmt::Debug::fmt(s, f))
+    }
+}
+
+macro_rules! impl_from_width {
+    ($($v:ident: $t:ty),*) => {
+        $(
+            impl From<Sixbit<$t>> for SixbitAny {
+                fn from(s: Sixbit<$t>) -> SixbitAny {
+                    SixbitAny::$v(s)
+                }
+            }
+        )*
+    };
+}
+
+impl_from_width!(Sixbit8: u8, Sixbit16: u16, Sixbit32: u32, Sixbit64: u64, Sixbit128: u128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn width(s: &str) -> usize {
+        encode_smallest(s).unwrap().width()
+    }
+
+    #[test]
+    fn smallest() {
+        assert_eq!(width(""), 8);
+        assert_eq!(width("a"), 8);
+        assert_eq!(width("ab"), 16);
+        assert_eq!(width("abc"), 32);
+        assert_eq!(width("_CAT_"), 32);
+        assert_eq!(width("NO_CARRIER"), 64);
+        assert_eq!(width("PRINTER_is_on_FIRE"), 128);
+
+        // Secondary pages skip the 2-bit tag widths.
+        assert_eq!(width("α"), 16);
+        assert_eq!(width("αβγ"), 64);
+        assert_eq!(width("ｲｸﾂｶﾉｲｸﾂｶﾉ"), 64);
+
+        // Primary pages other than Latin still use them.
+        assert_eq!(width("و"), 8);
+        assert_eq!(width("सपना"), 32);
+
+        // Chinese needs at least 15 bits per character.
+        assert_eq!(width("同事"), 32);
+        assert_eq!(width("合伙人"), 64);
+        assert_eq!(width("高速火车站"), 128);
+
+        assert_eq!(
+            encode_smallest("PRINTER_FULLY_OPERATIONAL_AND_ONLINE"),
+            Err(EncodeError::TooLong {
+                position: 21,
+                page: Page::Latin,
+                needed: 36,
+                available: 21,
+                fits_in: Some(256),
+            })
+        );
+        assert_eq!(
+            encode_smallest("αβγδεζηθικλ"),
+            Err(EncodeError::TooLong {
+                position: 10,
+                page: Page::Greek,
+                needed: 11,
+                available: 10,
+                fits_in: Some(192),
+            })
+        );
+        assert_eq!(
+            encode_smallest("高速火车站高速火车站"),
+            Err(EncodeError::TooLong {
+                position: 8,
+                page: Page::Chinese,
+                needed: 10,
+                available: 8,
+                fits_in: Some(192),
+            })
+        );
+        assert_eq!(
+            encode_smallest("sh@rk"),
+            Err(EncodeError::MissingFromPage {
+                position: 2,
+                c: '@',
+                page: Page::Latin,
+            })
+        );
+        assert_eq!(
+            encode_smallest("同a"),
+            Err(EncodeError::MissingFromPage {
+                position: 1,
+                c: 'a',
+                page: Page::Chinese,
+            })
+        );
+        assert_eq!(
+            encode_smallest("€"),
+            Err(EncodeError::NoCodePageFor {
+                position: 0,
+                c: '€'
+            })
+        );
+    }
+
+    #[test]
+    fn matches_encode() {
+        for w in [
+            "",
+            "OK",
+            "_CAT_",
+            "αλήθεια",
+            "содержать",
+            "ㅇㅜㅁ",
+            "合伙人",
+        ]
+        .iter()
+        {
+            let any = encode_smallest(w).unwrap();
+            assert_eq!(any.chars().collect::<String>(), *w);
+            assert_eq!(any.chars().rev().count(), any.char_len());
+            assert_eq!(any.to_string(), *w);
+            match any {
+                SixbitAny::Sixbit8(s) => assert_eq!(encode(w.chars()), Ok(s.get())),
+                SixbitAny::Sixbit16(s) => assert_eq!(encode(w.chars()), Ok(s.get())),
+                SixbitAny::Sixbit32(s) => assert_eq!(encode(w.chars()), Ok(s.get())),
+                SixbitAny::Sixbit64(s) => assert_eq!(encode(w.chars()), Ok(s.get())),
+                SixbitAny::Sixbit128(s) => assert_eq!(encode(w.chars()), Ok(s.get())),
+            }
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let words = [
+            "",
+            "A",
+            "AB",
+            "ABC",
+            "ABCD_EFGH_IJ",
+            "a",
+            "ab",
+            "abcde",
+            "α",
+            "αβγ",
+            "абв",
+            "ㅎ",
+            "ㅎㅏ",
+            "一",
+            "一丁",
+            "合伙人",
+            "ｦｧｨ",
+        ];
+        let mut packed: Vec<SixbitAny> =
+            words.iter().map(|w| encode_smallest(w).unwrap()).collect();
+        packed.sort();
+        let mut expected = words.to_vec();
+        expected.sort();
+        let got: Vec<String> = packed.iter().map(|p| p.to_string()).collect();
+        assert_eq!(got, expected);
+
+        // The same string at different widths is the same value.
+        let a = SixbitAny::from("a".parse::<Sixbit8>().unwrap());
+        let b = SixbitAny::from("a".parse::<Sixbit128>().unwrap());
+        assert_eq!(a, b);
+        let hash = |x: SixbitAny| {
+            let mut h = DefaultHasher::new();
+            x.hash(&mut h);
+            h.finish()
+        };
+        assert_eq!(hash(a), hash(b));
+        for w in words.iter() {
+            let mut h = DefaultHasher::new();
+            w.hash(&mut h);
+            assert_eq!(hash(encode_smallest(w).unwrap()), h.finish(), "{}", w);
+        }
+        // Hashed one after another, "a" then "ab" isn't "aa" then "b".
+        let pair = |x: &str, y: &str| {
+            let mut h = DefaultHasher::new();
+            encode_smallest(x).unwrap().hash(&mut h);
+            encode_smallest(y).unwrap().hash(&mut h);
+            h.finish()
+        };
+        assert_ne!(pair("a", "ab"), pair("aa", "b"));
+        assert!(b < SixbitAny::from("b".parse::<Sixbit8>().unwrap()));
+    }
+}
@@ -0,0 +1,363 @@
+use crate::{prefix_mask, PackedValue};
+
+// Searching slices of packed values. Since packed strings are just integers
+// that sort the same way their strings do, equality search, prefix search and
+// binary search all reduce to integer operations that vectorize well: a 256-bit
+// register holds four u64 strings or two u128 strings at once.
+//
+// Only u64 and u128 are supported, since those are the widths worth searching
+// in bulk. On x86_64 the AVX2 paths are used when the CPU has it, falling back
+// to SSE2 (which every x86_64 CPU has), and everywhere else to scalar code.
+pub trait BatchSearch: PackedValue + Ord {
+    #[doc(hidden)]
+    const ONES: Self;
+    #[doc(hidden)]
+    fn find_masked(hay: &[Self], mask: Self, key: Self) -> Option<usize>;
+    #[doc(hidden)]
+    fn count_less(hay: &[Self], key: Self) -> usize;
+}
+
+// Index of the first value equal to `key`.
+pub fn find_eq<N: BatchSearch>(hay: &[N], key: N) -> Option<usize> {
+    N::find_masked(hay, N::ONES, key)
+}
+
+// Index of the first value whose string starts with the string in `prefix`.
+pub fn find_prefix<N: BatchSearch>(hay: &[N], prefix: N) -> Option<usize> {
+    N::find_masked(hay, prefix_mask(prefix), prefix)
+}
+
+// Index of the first value that is not less than `key`, in a sorted slice.
+pub fn lower_bound<N: BatchSearch>(sorted: &[N], key: N) -> usize {
+    // Binary search down to a window that's a few vectors wide, then count
+    // the values below the key in it all at once.
+    const WINDOW: usize = 32;
+    let mut lo = 0;
+    let mut hi = sorted.len();
+    while hi - lo > WINDOW {
+        let mid = lo + (hi - lo) / 2;
+        if sorted[mid] < key {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo + N::count_less(&sorted[lo..hi], key)
+}
+
+fn find_masked_scalar<N: BatchSearch>(hay: &[N], mask: N, key: N) -> Option<usize> {
+    hay.iter().position(|&x| x & mask == key)
+}
+
+fn count_less_scalar<N: BatchSearch>(hay: &[N], key: N) -> usize {
+    hay.iter().filter(|&&x| x < key).count()
+}
+
+impl BatchSearch for u64 {
+    const ONES: u64 = !0;
+
+    fn find_masked(hay: &[u64], mask: u64, key: u64) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if x86::has_avx2() {
+                return unsafe { x86::find_masked_u64_avx2(hay, mask, key) };
+            }
+            unsafe { x86::find_masked_u64_sse2(hay, mask, key) }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        find_masked_scalar(hay, mask, key)
+    }
+
+    fn count_less(hay: &[u64], key: u64) -> usize {
+        // SSE2 has no 64-bit comparisons, so there's only an AVX2 path.
+        #[cfg(target_arch = "x86_64")]
+        {
+            if x86::has_avx2() {
+                return unsafe { x86::count_less_u64_avx2(hay, key) };
+            }
+        }
+        count_less_scalar(hay, key)
+    }
+}
+
+impl BatchSearch for u128 {
+    const ONES: u128 = !0;
+
+    fn find_masked(hay: &[u128], mask: u128, key: u128) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if x86::has_avx2() {
+                return unsafe { x86::find_masked_u128_avx2(hay, mask, key) };
+            }
+            unsafe { x86::find_masked_u128_sse2(hay, mask, key) }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        find_masked_scalar(hay, mask, key)
+    }
+
+    fn count_less(hay: &[u128], key: u128) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if x86::has_avx2() {
+                return unsafe { x86::count_less_u128_avx2(hay, key) };
+            }
+        }
+        count_less_scalar(hay, key)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    use super::{count_less_scalar, find_masked_scalar};
+
+    // Without std there's no runtime detection, so AVX2 is only used when the
+    // whole build targets it.
+    pub(super) fn has_avx2() -> bool {
+        #[cfg(feature = "std")]
+        {
+            is_x86_feature_detected!("avx2")
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            cfg!(target_feature = "avx2")
+        }
+    }
+
+    // The u128 paths treat each value as a pair of 64-bit lanes, low half
+    // first, since that's how they sit in memory on a little-endian machine.
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn find_masked_u64_avx2(hay: &[u64], mask: u64, key: u64) -> Option<usize> {
+        let m = _mm256_set1_epi64x(mask as i64);
+        let k = _mm256_set1_epi64x(key as i64);
+        let mut chunks = hay.chunks_exact(4);
+        let mut base = 0;
+        for c in &mut chunks {
+            let v = _mm256_loadu_si256(c.as_ptr() as *const __m256i);
+            let eq = _mm256_cmpeq_epi64(_mm256_and_si256(v, m), k);
+            let bits = _mm256_movemask_pd(_mm256_castsi256_pd(eq));
+            if bits != 0 {
+                return Some(base + bits.trailing_zeros() as usize);
+            }
+            base += 4;
+        }
+        find_masked_scalar(chunks.remainder(), mask, key).map(|i| base + i)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn find_masked_u64_sse2(hay: &[u64], mask: u64, key: u64) -> Option<usize> {
+        let m = _mm_set1_epi64x(mask as i64);
+        let k = _mm_set1_epi64x(key as i64);
+        let mut chunks = hay.chunks_exact(2);
+        let mut base = 0;
+        for c in &mut chunks {
+            let v = _mm_loadu_si128(c.as_ptr() as *const __m128i);
+            // No 64-bit compare in SSE2: compare 32-bit halves and require
+            // both halves of a lane to match.
+            let eq32 = _mm_cmpeq_epi32(_mm_and_si128(v, m), k);
+            let eq = _mm_and_si128(eq32, _mm_shuffle_epi32(eq32, 0b10_11_00_01));
+            let bits = _mm_movemask_pd(_mm_castsi128_pd(eq));
+            if bits != 0 {
+                return Some(base + bits.trailing_zeros() as usize);
+            }
+            base += 2;
+        }
+        find_masked_scalar(chunks.remainder(), mask, key).map(|i| base + i)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn find_masked_u128_avx2(
+        hay: &[u128],
+        mask: u128,
+        key: u128,
+    ) -> Option<usize> {
+        let m = _mm256_set_epi64x(
+            (mask >> 64) as i64,
+            mask as i64,
+            (mask >> 64) as i64,
+            mask as i64,
+        );
+        let k = _mm256_set_epi64x(
+            (key >> 64) as i64,
+            key as i64,
+            (key >> 64) as i64,
+            key as i64,
+        );
+        let mut chunks = hay.chunks_exact(2);
+        let mut base = 0;
+        for c in &mut chunks {
+            let v = _mm256_loadu_si256(c.as_ptr() as *const __m256i);
+            let eq = _mm256_cmpeq_epi64(_mm256_and_si256(v, m), k);
+            let bits = _mm256_movemask_pd(_mm256_castsi256_pd(eq));
+            // A value matches when both of its lanes do.
+            let both = bits & (bits >> 1) & 0b0101;
+            if both != 0 {
+                return Some(base + both.trailing_zeros() as usize / 2);
+            }
+            base += 2;
+        }
+        find_masked_scalar(chunks.remainder(), mask, key).map(|i| base + i)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn find_masked_u128_sse2(
+        hay: &[u128],
+        mask: u128,
+        key: u128,
+    ) -> Option<usize> {
+        let m = _mm_set_epi64x((mask >> 64) as i64, mask as i64);
+        let k = _mm_set_epi64x((key >> 64) as i64, key as i64);
+        for (i, x) in hay.iter().enumerate() {
+            let v = _mm_loadu_si128(x as *const u128 as *const __m128i);
+            let eq = _mm_cmpeq_epi8(_mm_and_si128(v, m), k);
+            if _mm_movemask_epi8(eq) == 0xffff {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    // AVX2 only has signed 64-bit comparison, so both sides get their sign
+    // bits flipped first to compare as unsigned.
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn count_less_u64_avx2(hay: &[u64], key: u64) -> usize {
+        let sign = _mm256_set1_epi64x(i64::MIN);
+        let k = _mm256_xor_si256(_mm256_set1_epi64x(key as i64), sign);
+        let mut chunks = hay.chunks_exact(4);
+        let mut n = 0;
+        for c in &mut chunks {
+            let v = _mm256_loadu_si256(c.as_ptr() as *const __m256i);
+            let lt = _mm256_cmpgt_epi64(k, _mm256_xor_si256(v, sign));
+            n += _mm256_movemask_pd(_mm256_castsi256_pd(lt)).count_ones() as usize;
+        }
+        n + count_less_scalar(chunks.remainder(), key)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn count_less_u128_avx2(hay: &[u128], key: u128) -> usize {
+        let sign = _mm256_set1_epi64x(i64::MIN);
+        let k = _mm256_set_epi64x(
+            (key >> 64) as i64,
+            key as i64,
+            (key >> 64) as i64,
+            key as i64,
+        );
+        let ks = _mm256_xor_si256(k, sign);
+        let mut chunks = hay.chunks_exact(2);
+        let mut n = 0;
+        for c in &mut chunks {
+            let v = _mm256_loadu_si256(c.as_ptr() as *const __m256i);
+            let lt = _mm256_movemask_pd(_mm256_castsi256_pd(_mm256_cmpgt_epi64(
+                ks,
+                _mm256_xor_si256(v, sign),
+            )));
+            let eq = _mm256_movemask_pd(_mm256_castsi256_pd(_mm256_cmpeq_epi64(v, k)));
+            // A value is less when its high lane is, or its high lane is
+            // equal and its low lane is less.
+            let less = (lt >> 1) | ((eq >> 1) & lt);
+            n += (less & 0b0101).count_ones() as usize;
+        }
+        n + count_less_scalar(chunks.remainder(), key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::EncodeSixbit;
+
+    // Short strings from small alphabets, so that plenty of them share
+    // prefixes or are equal.
+    fn words<N: PackedValue>() -> Vec<N> {
+        let mut rng = rand::thread_rng();
+        let mut v = Vec::new();
+        for _ in 0..200 {
+            let (alphabet, max) = if rng.gen() {
+                ("abcd_", N::NCHARS)
+            } else {
+                ("一丁七万", N::NWIDECHARS)
+            };
+            let alphabet: Vec<char> = alphabet.chars().collect();
+            let len = rng.gen_range(0..=max);
+            let s = (0..len).map(|_| alphabet[rng.gen_range(0..alphabet.len())]);
+            v.push(s.encode_sixbit().unwrap());
+        }
+        v
+    }
+
+    fn check<N: BatchSearch>(mut hay: Vec<N>) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let key = hay[rng.gen_range(0..hay.len())];
+            let len = rng.gen_range(0..hay.len());
+            let hay = &hay[..len];
+            assert_eq!(find_eq(hay, key), find_masked_scalar(hay, N::ONES, key));
+            assert_eq!(
+                find_prefix(hay, key),
+                find_masked_scalar(hay, prefix_mask(key), key)
+            );
+        }
+        hay.sort();
+        for _ in 0..100 {
+            let key = hay[rng.gen_range(0..hay.len())];
+            let len = rng.gen_range(0..hay.len());
+            let hay = &hay[..len];
+            let lb = lower_bound(hay, key);
+            assert_eq!(lb, hay.partition_point(|&x| x < key));
+            assert_eq!(lb, count_less_scalar(hay, key));
+        }
+    }
+
+    #[test]
+    fn against_scalar() {
+        check::<u64>(words());
+        check::<u128>(words());
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn each_x86_path() {
+        let hay64: Vec<u64> = words();
+        let hay128: Vec<u128> = words();
+        for i in 0..hay64.len() {
+            let (k64, k128) = (hay64[i], hay128[i]);
+            let (m64, m128) = (prefix_mask(k64), prefix_mask(k128));
+            let want64 = find_masked_scalar(&hay64, m64, k64);
+            let want128 = find_masked_scalar(&hay128, m128, k128);
+            unsafe {
+                assert_eq!(x86::find_masked_u64_sse2(&hay64, m64, k64), want64);
+                assert_eq!(x86::find_masked_u128_sse2(&hay128, m128, k128), want128);
+                if x86::has_avx2() {
+                    assert_eq!(x86::find_masked_u64_avx2(&hay64, m64, k64), want64);
+                    assert_eq!(x86::find_masked_u128_avx2(&hay128, m128, k128), want128);
+                    assert_eq!(
+                        x86::count_less_u64_avx2(&hay64, k64),
+                        count_less_scalar(&hay64, k64)
+                    );
+                    assert_eq!(
+                        x86::count_less_u128_avx2(&hay128, k128),
+                        count_less_scalar(&hay128, k128)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn prefixes() {
+        let enc = |s: &str| s.chars().encode_sixbit::<u64>().unwrap();
+        let hay = [enc("abc"), enc("hello"), enc("合伙人"), enc("合伙")];
+        assert_eq!(find_prefix(&hay, enc("")), Some(0));
+        assert_eq!(find_prefix(&hay, enc("he")), Some(1));
+        assert_eq!(find_prefix(&hay, enc("hello")), Some(1));
+        assert_eq!(find_prefix(&hay, enc("合")), Some(2));
+        assert_eq!(find_prefix(&hay, enc("hallo")), None);
+        assert_eq!(find_eq(&hay, enc("合伙")), Some(3));
+        assert_eq!(find_eq(&hay, enc("合")), None);
+    }
+}
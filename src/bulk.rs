@@ -0,0 +1,154 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{encode_utf8, DecodeSixbit, EncodeError, PackedValue};
+
+// Encoding and decoding whole columns of strings at once. The work is split
+// into fixed-size chunks, each producing its own results, which are put back
+// together in order; with the `rayon` feature the chunks are done in
+// parallel, and since neither the chunks nor the order of their results
+// depend on the thread count, the output is the same either way.
+
+const CHUNK: usize = 4096;
+
+fn encode_chunk<N: PackedValue>(
+    base: usize,
+    strs: &[&str],
+    out: &mut [N],
+) -> Vec<(usize, EncodeError)> {
+    let mut errors = Vec::new();
+    for (i, (s, n)) in strs.iter().zip(out.iter_mut()).enumerate() {
+        match encode_utf8(s.as_bytes()) {
+            Ok(packed) => *n = packed,
+            Err(e) => errors.push((base + i, e)),
+        }
+    }
+    errors
+}
+
+// Encode every string, giving a value for each (the empty string for those
+// that failed) and the index and error of each failure, in order.
+pub fn encode_all<N>(strs: &[&str]) -> (Vec<N>, Vec<(usize, EncodeError)>)
+where
+    N: PackedValue + Send,
+{
+    let mut packed = vec![N::truncating_cast_from(0); strs.len()];
+    #[cfg(feature = "rayon")]
+    let errors: Vec<_> = strs
+        .par_chunks(CHUNK)
+        .zip(packed.par_chunks_mut(CHUNK))
+        .enumerate()
+        .map(|(c, (strs, out))| encode_chunk(c * CHUNK, strs, out))
+        .collect();
+    #[cfg(not(feature = "rayon"))]
+    let errors: Vec<_> = strs
+        .chunks(CHUNK)
+        .zip(packed.chunks_mut(CHUNK))
+        .enumerate()
+        .map(|(c, (strs, out))| encode_chunk(c * CHUNK, strs, out))
+        .collect();
+    (packed, errors.concat())
+}
+
+// The UTF-8 of a chunk of values, and where each one ends in it.
+fn decode_chunk<N: PackedValue>(packed: &[N]) -> (Vec<u8>, Vec<usize>) {
+    let mut bytes = vec![0u8; packed.len() * 3 * N::NCHARS];
+    let mut ends = Vec::with_capacity(packed.len());
+    let mut at = 0;
+    for &n in packed {
+        at += n.decode_utf8_into(&mut bytes[at..]);
+        ends.push(at);
+    }
+    bytes.truncate(at);
+    (bytes, ends)
+}
+
+// Decode every value into one string, with the offsets of each: value `i` is
+// `arena[offsets[i]..offsets[i + 1]]`.
+pub fn decode_all<N>(packed: &[N]) -> (String, Vec<usize>)
+where
+    N: PackedValue + Sync,
+{
+    #[cfg(feature = "rayon")]
+    let chunks: Vec<_> = packed.par_chunks(CHUNK).map(decode_chunk).collect();
+    #[cfg(not(feature = "rayon"))]
+    let chunks: Vec<_> = packed.chunks(CHUNK).map(decode_chunk).collect();
+
+    let mut bytes = Vec::with_capacity(chunks.iter().map(|(b, _)| b.len()).sum());
+    let mut offsets = Vec::with_capacity(packed.len() + 1);
+    offsets.push(0);
+    for (b, ends) in chunks {
+        let base = bytes.len();
+        bytes.extend_from_slice(&b);
+        offsets.extend(ends.iter().map(|e| base + e));
+    }
+    // Safe because every value decoded to whole chars.
+    (unsafe { String::from_utf8_unchecked(bytes) }, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode, U192};
+    use rand::{Rng, SeedableRng};
+
+    const WORDS: [&str; 10] = [
+        "",
+        "id",
+        "NO_CARRIER",
+        "PRINTER_is_on_FIRE",
+        "αλήθεια",
+        "合伙人",
+        "ｲｸﾂｶﾉ",
+        "sh@rk",
+        "€",
+        "किंकर्तव्यविमूढ़",
+    ];
+
+    fn column(len: usize) -> Vec<&'static str> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0xb01c);
+        (0..len)
+            .map(|_| WORDS[rng.gen_range(0..WORDS.len())])
+            .collect()
+    }
+
+    fn check<N: PackedValue + Send + Sync>(strs: &[&str]) {
+        let (packed, errors) = encode_all::<N>(strs);
+        assert_eq!(packed.len(), strs.len());
+        let mut expect_errors = Vec::new();
+        for (i, s) in strs.iter().enumerate() {
+            match encode::<N, _>(s.chars()) {
+                Ok(n) => assert_eq!(packed[i], n),
+                Err(e) => {
+                    assert_eq!(packed[i], N::truncating_cast_from(0));
+                    expect_errors.push((i, e));
+                }
+            }
+        }
+        assert_eq!(errors, expect_errors);
+
+        let (arena, offsets) = decode_all(&packed);
+        assert_eq!(offsets.len(), packed.len() + 1);
+        for (i, n) in packed.iter().enumerate() {
+            let s: String = n.decode_sixbit().collect();
+            assert_eq!(&arena[offsets[i]..offsets[i + 1]], s);
+        }
+        assert_eq!(offsets[packed.len()], arena.len());
+    }
+
+    #[test]
+    fn same_as_encode() {
+        check::<u64>(&[]);
+        check::<u64>(&WORDS);
+        // Enough for several chunks, and a partial one.
+        let strs = column(3 * CHUNK + 17);
+        check::<u32>(&strs);
+        check::<u64>(&strs);
+        check::<u128>(&strs);
+        check::<U192>(&strs);
+    }
+}
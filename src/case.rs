@@ -0,0 +1,179 @@
+use crate::{tag_of, DecodeSixbit, PackedValue};
+
+// The cased pages keep each case in a contiguous run of codes, in the same
+// order, so changing case is adding or subtracting a fixed offset to each
+// code in the right range, without going through chars:
+//
+//   Latin     A-Z at 11-36, a-z at 38-63
+//   Greek     Ά-Ώ at 1-7, Α-Ω at 8-31, ά-ί at 32-35, α-ω at 36-60, ό-ώ at 61-63
+//   Cyrillic  А-Я at 1-32, а-я (without ъ) at 33-63
+//
+// Uppercasing is defined everywhere on these pages, but lowercasing isn't:
+// Σ could be σ or ς depending on where it is in a word, and Ъ has nowhere to
+// go because the page doesn't have ъ. Those, and every other page, give None.
+
+const LATIN: usize = 0;
+const GREEK: usize = 1;
+const CYRILLIC: usize = 2;
+
+fn upper_code(page: usize, code: usize) -> Option<usize> {
+    match (page, code) {
+        (LATIN, 38..=63) => Some(code - 27),
+        (GREEK, 32..=35) => Some(code - 31),
+        (GREEK, 36..=52) => Some(code - 28),
+        // Both final and medial sigma.
+        (GREEK, 53..=54) => Some(25),
+        (GREEK, 55..=60) => Some(code - 29),
+        (GREEK, 61..=63) => Some(code - 56),
+        (CYRILLIC, 33..=58) => Some(code - 32),
+        (CYRILLIC, 59..=63) => Some(code - 31),
+        (LATIN, _) | (GREEK, _) | (CYRILLIC, _) => Some(code),
+        _ => None,
+    }
+}
+
+fn lower_code(page: usize, code: usize) -> Option<usize> {
+    match (page, code) {
+        (LATIN, 11..=36) => Some(code + 27),
+        (GREEK, 1..=4) => Some(code + 31),
+        (GREEK, 5..=7) => Some(code + 56),
+        (GREEK, 8..=24) => Some(code + 28),
+        (GREEK, 25) => None,
+        (GREEK, 26..=31) => Some(code + 29),
+        (CYRILLIC, 1..=26) => Some(code + 32),
+        (CYRILLIC, 27) => None,
+        (CYRILLIC, 28..=32) => Some(code + 31),
+        (LATIN, _) | (GREEK, _) | (CYRILLIC, _) => Some(code),
+        _ => None,
+    }
+}
+
+fn map_codes<N>(n: N, map: fn(usize, usize) -> Option<usize>) -> Option<N>
+where
+    N: PackedValue,
+{
+    let page = tag_of(n);
+    // Before reading any codes, which in other pages may not be 6 bits.
+    if page > CYRILLIC {
+        return None;
+    }
+    let mut it = n.decode_sixbit();
+    let mut out = N::truncating_cast_from(if N::NTAGBITS == 2 { page >> 2 } else { page });
+    // Codes past the end are zero, which every cased page maps to itself.
+    for _ in 0..N::NCHARS {
+        let code = map(page, it.peek_code())?;
+        it.tmp <<= 6;
+        out <<= 6;
+        out |= N::truncating_cast_from(code);
+    }
+    out <<= N::NPADBITS;
+    Some(out)
+}
+
+pub(crate) fn to_uppercase<N: PackedValue>(n: N) -> Option<N> {
+    map_codes(n, upper_code)
+}
+
+pub(crate) fn to_lowercase<N: PackedValue>(n: N) -> Option<N> {
+    map_codes(n, lower_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{encode, PackedValue, PAGES};
+    use core::cmp::Ordering;
+
+    fn enc(s: &str) -> u64 {
+        encode(s.chars()).unwrap()
+    }
+
+    // Check the code arithmetic against the standard library's case mapping,
+    // for every code in every cased page.
+    #[test]
+    fn every_code() {
+        for (page, chars) in PAGES.iter().enumerate().take(3) {
+            for &c in chars.iter().skip(1).filter(|&&c| c != '\u{ffff}') {
+                let n = encode::<u64, _>(core::iter::once(c)).unwrap();
+                let upper = n.to_uppercase();
+                assert_eq!(encode(c.to_uppercase()).ok(), upper, "{} {:?}", page, c);
+                match n.to_lowercase() {
+                    Some(lower) => assert_eq!(encode(c.to_lowercase()), Ok(lower)),
+                    None => assert!(c == 'Σ' || c == 'Ъ', "{:?}", c),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversion() {
+        assert_eq!(enc("NO_CARRIER").to_lowercase(), Some(enc("no_carrier")));
+        assert_eq!(enc("x86_64").to_uppercase(), Some(enc("X86_64")));
+        assert_eq!(enc("αλήθεια").to_uppercase(), Some(enc("ΑΛΉΘΕΙΑ")));
+        assert_eq!(enc("ΚΌΣΜΟΣ").to_lowercase(), None);
+        assert_eq!(enc("ΚΌΜΑ").to_lowercase(), Some(enc("κόμα")));
+        assert_eq!(enc("содержать").to_uppercase(), Some(enc("СОДЕРЖАТЬ")));
+        assert_eq!(enc("ОБЪЕКТ").to_lowercase(), None);
+        assert_eq!(enc("").to_lowercase(), Some(0));
+        assert_eq!(enc("לעשות").to_lowercase(), None);
+        assert_eq!(enc("合伙人").to_uppercase(), None);
+
+        // Every width.
+        let a: u8 = encode("a".chars()).unwrap();
+        assert_eq!(a.to_uppercase(), encode("A".chars()).ok());
+        let a: u16 = encode("ωΩ".chars()).unwrap();
+        assert_eq!(a.to_uppercase(), encode("ΩΩ".chars()).ok());
+        let a: u32 = encode("Hello".chars()).unwrap();
+        assert_eq!(a.to_lowercase(), encode("hello".chars()).ok());
+        let a: u128 = encode("PRINTER_is_on_FIRE".chars()).unwrap();
+        assert_eq!(a.to_uppercase(), encode("PRINTER_IS_ON_FIRE".chars()).ok());
+    }
+
+    // Uncased pages give None without reading their codes, including Chinese
+    // in a u8, where a 15-bit code doesn't fit.
+    #[test]
+    fn uncased() {
+        for a in 0..=255u8 {
+            let cased = a >> 6 == 0;
+            assert_eq!(a.to_uppercase().is_some(), cased, "{:#x}", a);
+            assert_eq!(a.to_lowercase().is_some(), cased, "{:#x}", a);
+            assert_eq!(a.eq_ignore_case(a).is_some(), cased, "{:#x}", a);
+            assert_eq!(a.cmp_ignore_case(0).is_some(), cased, "{:#x}", a);
+        }
+        let a: u32 = encode("同事".chars()).unwrap();
+        assert_eq!(a.to_uppercase(), None);
+        assert_eq!(a.to_lowercase(), None);
+        assert_eq!(a.eq_ignore_case(a), None);
+        assert_eq!(a.cmp_ignore_case(0), None);
+        let a = enc("合伙人");
+        assert_eq!(a.to_lowercase(), None);
+        assert_eq!(a.eq_ignore_case(a), None);
+        assert_eq!(a.cmp_ignore_case(enc("合")), None);
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(enc("Hello").eq_ignore_case(enc("hELLO")), Some(true));
+        assert_eq!(enc("Hello").eq_ignore_case(enc("hELL")), Some(false));
+        assert_eq!(enc("ΟΔΌΣ").eq_ignore_case(enc("οδός")), Some(true));
+        assert_eq!(enc("ΟΔΌΣ").eq_ignore_case(enc("οδόσ")), Some(true));
+        assert_eq!(enc("לעשות").eq_ignore_case(enc("לעשות")), None);
+
+        assert_eq!(
+            enc("apple").cmp_ignore_case(enc("Banana")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            enc("ZEBRA").cmp_ignore_case(enc("yak")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            enc("abc").cmp_ignore_case(enc("ABCD")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            enc("щука").cmp_ignore_case(enc("ЩУКА")),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(enc("abc").cmp_ignore_case(enc("合伙人")), None);
+    }
+}
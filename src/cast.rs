@@ -0,0 +1,222 @@
+use crate::{validate, DecodeSixbit, EncodeError, PackedValue, Page, CHINESE_4BIT_TAG, U192, U256};
+
+// Casting between packed widths works directly on the codes: the 4-bit tags
+// keep the 2-bit tags as their high bits, so a tag only changes width, and the
+// 6-bit or 15-bit codes are copied across unchanged.
+//
+// `Widen` marks the pairs where that can never fail: the target holds at least
+// as many characters and every page of the source. Widths with 4-bit tags can
+// hold pages that 2-bit tags can't, so u16 and u64 values only widen to u64
+// and the wide values; use `try_narrow` to take them to a u32 or u128.
+pub trait Widen<N: PackedValue>: PackedValue {}
+
+impl Widen<u16> for u8 {}
+impl Widen<u32> for u8 {}
+impl Widen<u64> for u8 {}
+impl Widen<u128> for u8 {}
+impl Widen<u64> for u16 {}
+impl Widen<u64> for u32 {}
+impl Widen<u128> for u32 {}
+impl Widen<U192> for u8 {}
+impl Widen<U192> for u16 {}
+impl Widen<U192> for u32 {}
+impl Widen<U192> for u64 {}
+impl Widen<U192> for u128 {}
+impl Widen<U256> for u8 {}
+impl Widen<U256> for u16 {}
+impl Widen<U256> for u32 {}
+impl Widen<U256> for u64 {}
+impl Widen<U256> for u128 {}
+impl Widen<U256> for U192 {}
+
+pub fn widen<A, B>(a: A) -> B
+where
+    A: Widen<B>,
+    B: PackedValue,
+{
+    // This should always succeed, whatever the codes are. There's a bug if
+    // not.
+    cast(a).expect("sixbit::widen")
+}
+
+// Cast between any two widths, failing the same way `encode` would have if
+// the string was encoded at the target width in the first place: with
+// `PageUnavailable` when the page needs a 4-bit tag and the target only has 2
+// bits, or `TooLong` when there isn't room for every character. A value that
+// isn't a valid string at its own width fails with `InvalidValue`.
+pub fn try_narrow<A, B>(a: A) -> Result<B, EncodeError>
+where
+    A: PackedValue,
+    B: PackedValue,
+{
+    validate(a).map_err(|error| EncodeError::InvalidValue { error })?;
+    cast(a)
+}
+
+// Copy the tag and codes across, whether or not they make a valid string.
+fn cast<A, B>(a: A) -> Result<B, EncodeError>
+where
+    A: PackedValue,
+    B: PackedValue,
+{
+    let mut it = a.decode_sixbit();
+    let mut out = B::truncating_cast_from(0);
+    let (bits, max) = if it.tag == CHINESE_4BIT_TAG {
+        (15, A::NWIDECHARS)
+    } else {
+        (6, A::NCHARS)
+    };
+    if max == 0 || it.peek_code() == 0 {
+        // Zero-length strings map to page 0, code 0.
+        return Ok(out);
+    }
+    let mut tag = it.tag;
+    if B::NTAGBITS == 2 {
+        if tag & 0b11 != 0 {
+            return Err(EncodeError::PageUnavailable {
+                position: 0,
+                page: Page::from_index(tag),
+            });
+        }
+        tag >>= 2;
+    }
+    out |= B::truncating_cast_from(tag);
+    let mut rembits = B::NCHARBITS;
+    for pos in 0..max {
+        let code = it.peek_code();
+        if code == 0 {
+            break;
+        }
+        if rembits < bits {
+            let available = if bits == 15 { B::NWIDECHARS } else { B::NCHARS };
+            return Err(EncodeError::too_long(pos, it.tag, a.char_len(), available));
+        }
+        out <<= bits;
+        out |= B::truncating_cast_from(code);
+        it.tmp <<= bits;
+        rembits -= bits;
+    }
+    // Pad remainder.
+    out <<= rembits + B::NPADBITS;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecodeError, EncodeSixbit};
+
+    fn enc<N: PackedValue>(s: &str) -> N {
+        s.chars().encode_sixbit::<N>().unwrap()
+    }
+
+    #[test]
+    fn widening() {
+        assert_eq!(widen::<u8, u16>(enc("a")), enc::<u16>("a"));
+        assert_eq!(widen::<u8, u128>(enc("و")), enc::<u128>("و"));
+        assert_eq!(widen::<u16, u64>(enc("γη")), enc::<u64>("γη"));
+        assert_eq!(widen::<u32, u64>(enc("同事")), enc::<u64>("同事"));
+        assert_eq!(widen::<u32, u128>(enc("सपना")), enc::<u128>("सपना"));
+        assert_eq!(widen::<u8, u64>(0), 0);
+
+        assert_eq!(widen::<u8, U192>(enc("a")), enc::<U192>("a"));
+        assert_eq!(widen::<u16, U256>(enc("ﾔﾙ")), enc::<U256>("ﾔﾙ"));
+        assert_eq!(widen::<u64, U192>(enc("αλήθεια")), enc::<U192>("αλήθεια"));
+        let long = "PRINTER_is_on_FIRE_ok";
+        assert_eq!(widen::<u128, U192>(enc(long)), enc::<U192>(long));
+        assert_eq!(
+            widen::<u128, U256>(enc("高速火车站再见一")),
+            enc::<U256>("高速火车站再见一")
+        );
+        let longer = "PRINTER_is_on_FIRE_and_the_fax_";
+        assert_eq!(widen::<U192, U256>(enc(longer)), enc::<U256>(longer));
+        assert_eq!(
+            try_narrow::<U256, u64>(enc("NO_CARRIER")),
+            Ok(enc::<u64>("NO_CARRIER"))
+        );
+    }
+
+    #[test]
+    fn narrowing() {
+        assert_eq!(
+            try_narrow::<u128, u32>(enc("_CAT_")),
+            Ok(enc::<u32>("_CAT_"))
+        );
+        assert_eq!(
+            try_narrow::<u128, u64>(enc("合伙人")),
+            Ok(enc::<u64>("合伙人"))
+        );
+        assert_eq!(
+            try_narrow::<u64, u128>(enc("ATDT_123")),
+            Ok(enc::<u128>("ATDT_123"))
+        );
+        assert_eq!(try_narrow::<u64, u8>(enc("")), Ok(0));
+
+        // Secondary pages only have room in 4-bit tags.
+        assert_eq!(
+            try_narrow::<u64, u128>(enc("αλήθεια")),
+            Err(EncodeError::PageUnavailable {
+                position: 0,
+                page: Page::Greek,
+            })
+        );
+        assert_eq!(
+            try_narrow::<u16, u32>(enc("ﾔﾙ")),
+            Err(EncodeError::PageUnavailable {
+                position: 0,
+                page: Page::HalfwidthKana,
+            })
+        );
+
+        // Too many characters for the target.
+        assert_eq!(
+            try_narrow::<u128, u64>(enc("Printer_Working")),
+            Err(EncodeError::TooLong {
+                position: 10,
+                page: Page::Latin,
+                needed: 15,
+                available: 10,
+                fits_in: Some(128),
+            })
+        );
+        assert_eq!(
+            try_narrow::<u64, u32>(enc("合伙人")),
+            Err(EncodeError::TooLong {
+                position: 2,
+                page: Page::Chinese,
+                needed: 3,
+                available: 2,
+                fits_in: Some(64),
+            })
+        );
+        assert_eq!(
+            try_narrow::<u32, u16>(enc("同")),
+            Err(EncodeError::TooLong {
+                position: 0,
+                page: Page::Chinese,
+                needed: 1,
+                available: 0,
+                fits_in: Some(32),
+            })
+        );
+
+        // Values that aren't strings at all.
+        assert_eq!(
+            try_narrow::<u64, u128>(0x5 << 60 | 1 << 54),
+            Err(EncodeError::InvalidValue {
+                error: DecodeError::ReservedTag(5),
+            })
+        );
+        let abc: u64 = enc("abc");
+        assert_eq!(
+            try_narrow::<u64, u128>(abc | 1),
+            Err(EncodeError::InvalidValue {
+                error: DecodeError::NonCanonicalPadding,
+            })
+        );
+        assert_eq!(
+            try_narrow::<u64, u128>(abc | 1).unwrap_err().to_string(),
+            "invalid packed value: nonzero bits after the end of the string"
+        );
+    }
+}
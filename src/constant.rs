@@ -0,0 +1,276 @@
+use crate::{
+    code_in_page, lookup, EncodeError, Page, CHINESE_2BIT_TAG, CHINESE_4BIT_TAG, CHINESE_HI,
+    CHINESE_LO,
+};
+
+// Compile-time encoding. This is the same algorithm as `encode`, restricted to
+// what's allowed in a `const fn`: no traits, no iterators, and no generics over
+// the packed type, so it works on a u128 and a bit-width and gets cast down by
+// each of the typed wrappers at the end.
+
+pub const fn encode_str_u8(s: &str) -> Result<u8, EncodeError> {
+    match encode_str(s, 8) {
+        Ok(n) => Ok(n as u8),
+        Err(e) => Err(e),
+    }
+}
+
+pub const fn encode_str_u16(s: &str) -> Result<u16, EncodeError> {
+    match encode_str(s, 16) {
+        Ok(n) => Ok(n as u16),
+        Err(e) => Err(e),
+    }
+}
+
+pub const fn encode_str_u32(s: &str) -> Result<u32, EncodeError> {
+    match encode_str(s, 32) {
+        Ok(n) => Ok(n as u32),
+        Err(e) => Err(e),
+    }
+}
+
+pub const fn encode_str_u64(s: &str) -> Result<u64, EncodeError> {
+    match encode_str(s, 64) {
+        Ok(n) => Ok(n as u64),
+        Err(e) => Err(e),
+    }
+}
+
+pub const fn encode_str_u128(s: &str) -> Result<u128, EncodeError> {
+    encode_str(s, 128)
+}
+
+/// Encodes a string literal at compile time, as a `u64` by default or as
+/// any other packed width if one is given first. Strings that don't encode
+/// are compile errors.
+///
+/// ```
+/// use sixbit::sixbit;
+/// const CARRIER: u64 = sixbit!("NO_CARRIER");
+/// static WORDS: [u32; 2] = [sixbit!(u32, "同事"), sixbit!(u32, "سلام")];
+/// ```
+///
+/// ```compile_fail
+/// use sixbit::sixbit;
+/// const TOO_LONG: u64 = sixbit!("PRINTER_FULLY_OPERATIONAL");
+/// ```
+#[macro_export]
+macro_rules! sixbit {
+    ($s:expr) => {
+        $crate::sixbit!(u64, $s)
+    };
+    ($t:ty, $s:expr) => {{
+        const PACKED: $t = $crate::__encode_str_or_panic($s, <$t>::BITS) as $t;
+        PACKED
+    }};
+}
+
+#[doc(hidden)]
+pub const fn __encode_str_or_panic(s: &str, nbits: u32) -> u128 {
+    match encode_str(s, nbits as usize) {
+        Ok(n) => n,
+        Err(EncodeError::TooLong { .. }) => panic!("sixbit!: string is too long"),
+        Err(EncodeError::NoCodePageFor { .. }) => panic!("sixbit!: no code page for string"),
+        Err(EncodeError::PageUnavailable { .. }) => panic!("sixbit!: code page unavailable"),
+        Err(EncodeError::MissingFromPage { .. }) => {
+            panic!("sixbit!: character missing from page")
+        }
+        // A &str is always UTF-8.
+        Err(EncodeError::InvalidUtf8 { .. }) | Err(EncodeError::InvalidValue { .. }) => {
+            unreachable!()
+        }
+    }
+}
+
+// Decode the UTF-8 sequence starting at b[i], returning the code point and
+// its length. The input came from a &str so it's known to be well-formed.
+const fn decode_utf8(b: &[u8], i: usize) -> (u32, usize) {
+    let b0 = b[i] as u32;
+    if b0 < 0x80 {
+        (b0, 1)
+    } else if b0 < 0xe0 {
+        (((b0 & 0x1f) << 6) | (b[i + 1] as u32 & 0x3f), 2)
+    } else if b0 < 0xf0 {
+        let c = ((b0 & 0x0f) << 12) | ((b[i + 1] as u32 & 0x3f) << 6);
+        (c | (b[i + 2] as u32 & 0x3f), 3)
+    } else {
+        let c = ((b0 & 0x07) << 18) | ((b[i + 1] as u32 & 0x3f) << 12);
+        (
+            c | ((b[i + 2] as u32 & 0x3f) << 6) | (b[i + 3] as u32 & 0x3f),
+            4,
+        )
+    }
+}
+
+// The number of chars in b[i..], counting the bytes that start one.
+const fn count_chars(b: &[u8], mut i: usize) -> usize {
+    let mut n = 0;
+    while i < b.len() {
+        if b[i] & 0xc0 != 0x80 {
+            n += 1;
+        }
+        i += 1;
+    }
+    n
+}
+
+const fn to_char(c: u32) -> char {
+    match char::from_u32(c) {
+        Some(c) => c,
+        None => '\u{fffd}',
+    }
+}
+
+const fn encode_str(s: &str, nbits: usize) -> Result<u128, EncodeError> {
+    let ntagbits = nbits % 6;
+    let b = s.as_bytes();
+    let mut i = 0;
+    let mut pos = 0;
+    // Zero-length strings map to page 0, code 0.
+    if b.is_empty() {
+        return Ok(0);
+    }
+    let (init, _) = decode_utf8(b, 0);
+
+    // First handle special case of Chinese characters, which are encoded as deltas.
+    if CHINESE_LO as u32 <= init && init <= CHINESE_HI as u32 {
+        let mut out = if ntagbits == 2 {
+            CHINESE_2BIT_TAG
+        } else {
+            CHINESE_4BIT_TAG
+        } as u128;
+        let mut rembits = nbits - ntagbits;
+        while i < b.len() {
+            let (c, n) = decode_utf8(b, i);
+            i += n;
+            if rembits < 15 {
+                let needed = pos + 1 + count_chars(b, i);
+                let available = (nbits - ntagbits) / 15;
+                return Err(EncodeError::too_long(
+                    pos,
+                    CHINESE_4BIT_TAG,
+                    needed,
+                    available,
+                ));
+            }
+            if c < CHINESE_LO as u32 || c > CHINESE_HI as u32 {
+                return Err(EncodeError::MissingFromPage {
+                    position: pos,
+                    c: to_char(c),
+                    page: Page::Chinese,
+                });
+            }
+            out = (out << 15) | (c - CHINESE_LO as u32 + 1) as u128;
+            rembits -= 15;
+            pos += 1;
+        }
+        return Ok(out << rembits);
+    }
+
+    let p = match lookup(to_char(init)) {
+        Some((p, _)) => p,
+        None => {
+            return Err(EncodeError::NoCodePageFor {
+                position: 0,
+                c: to_char(init),
+            })
+        }
+    };
+    let mut tag = p;
+    if ntagbits == 2 {
+        if tag & 0b11 != 0 {
+            return Err(EncodeError::PageUnavailable {
+                position: 0,
+                page: Page::from_index(tag),
+            });
+        }
+        tag >>= 2;
+    }
+    let mut out = tag as u128;
+    let mut rem = nbits / 6;
+    while i < b.len() {
+        let (c, n) = decode_utf8(b, i);
+        i += n;
+        if rem == 0 {
+            let needed = pos + 1 + count_chars(b, i);
+            return Err(EncodeError::too_long(pos, p, needed, nbits / 6));
+        }
+        match code_in_page(p, to_char(c)) {
+            None => {
+                return Err(EncodeError::MissingFromPage {
+                    position: pos,
+                    c: to_char(c),
+                    page: Page::from_index(p),
+                })
+            }
+            Some(code) => {
+                out = (out << 6) | code as u128;
+                rem -= 1;
+            }
+        }
+        pos += 1;
+    }
+    Ok(out << (6 * rem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode, PackedValue};
+
+    const CARRIER: u64 = sixbit!("NO_CARRIER");
+    static KEYWORDS: [u16; 3] = [sixbit!(u16, "OK"), sixbit!(u16, "γη"), sixbit!(u16, "ﾔﾙ")];
+
+    fn check<N: PackedValue>(s: &str, f: fn(&str) -> Result<N, EncodeError>) {
+        assert_eq!(f(s), encode::<N, _>(s.chars()), "{:?}", s);
+    }
+
+    #[test]
+    fn matches_runtime() {
+        let words = [
+            "",
+            "1",
+            "OK",
+            "_CAT_",
+            "NO_CARRIER",
+            "PRINTER_is_on_FIRE",
+            "PRINTER_FULLY_OPERATIONAL",
+            "sh@rk",
+            "©2018",
+            "αλήθεια",
+            "ΨΩ",
+            "содержать",
+            "לעשות",
+            "و",
+            "محافظت",
+            "किंकर्तव्यविमूढ़",
+            "ㅇㅜㅁ",
+            "同事",
+            "合伙人",
+            "高速火车站",
+            "一丁",
+            "合a",
+            "ｲｸﾂｶﾉ",
+            "\0",
+            "\u{ffff}",
+        ];
+        for w in words.iter() {
+            check(w, encode_str_u8);
+            check(w, encode_str_u16);
+            check(w, encode_str_u32);
+            check(w, encode_str_u64);
+            check(w, encode_str_u128);
+        }
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(Ok(CARRIER), encode("NO_CARRIER".chars()));
+        assert_eq!(Ok(KEYWORDS[1]), encode("γη".chars()));
+        const OK: u16 = sixbit!(u16, "OK");
+        match KEYWORDS[0] {
+            OK => (),
+            _ => panic!("no match"),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{encode, DecodeSixbit, DecodeSixbitIter, EncodeError, PackedValue};
+
+// The Hangul page holds compatibility jamo rather than syllables, so Korean
+// text is split into its jamo on the way in and put back together on the way
+// out. Precomposed syllables U+AC00-U+D7A3 are laid out arithmetically as
+// (initial * 21 + vowel) * 28 + final, where final 0 means there isn't one;
+// the tables below map those indices to the compatibility jamo.
+//
+// A syllable always starts with a consonant, so decoding reads a consonant
+// and vowel as the start of a syllable and takes a following consonant as
+// its final only if that isn't itself followed by a vowel. That recovers any
+// sequence of syllables exactly; jamo that don't form syllables are passed
+// through, though a consonant-vowel pair given as separate jamo will come
+// back as a syllable.
+//
+// Packed values sort in jamo order, which matches syllable order except where
+// one string has a syllable without a final and the other has the same
+// syllable with one: "가나" is ㄱㅏㄴㅏ and "각" is ㄱㅏㄱ, so "각" sorts first
+// even though "가" < "각".
+
+const SYLLABLE_LO: u32 = 0xac00;
+const SYLLABLE_HI: u32 = 0xd7a3;
+const NVOWELS: u32 = 21;
+const NFINALS: u32 = 28;
+const VOWEL_LO: u32 = 0x314f;
+const VOWEL_HI: u32 = 0x3163;
+
+#[rustfmt::skip]
+const INITIALS: [char; 19] = [
+    'ㄱ', 'ㄲ', 'ㄴ', 'ㄷ', 'ㄸ', 'ㄹ', 'ㅁ', 'ㅂ', 'ㅃ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅉ', 'ㅊ', 'ㅋ',
+    'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+#[rustfmt::skip]
+const FINALS: [char; 28] = [
+    '\0', 'ㄱ', 'ㄲ', 'ㄳ', 'ㄴ', 'ㄵ', 'ㄶ', 'ㄷ', 'ㄹ', 'ㄺ', 'ㄻ', 'ㄼ', 'ㄽ', 'ㄾ', 'ㄿ', 'ㅀ',
+    'ㅁ', 'ㅂ', 'ㅄ', 'ㅅ', 'ㅆ', 'ㅇ', 'ㅈ', 'ㅊ', 'ㅋ', 'ㅌ', 'ㅍ', 'ㅎ',
+];
+
+fn initial_index(c: char) -> Option<u32> {
+    INITIALS.iter().position(|&i| i == c).map(|i| i as u32)
+}
+
+fn vowel_index(c: char) -> Option<u32> {
+    let c = c as u32;
+    if (VOWEL_LO..=VOWEL_HI).contains(&c) {
+        Some(c - VOWEL_LO)
+    } else {
+        None
+    }
+}
+
+fn final_index(c: char) -> Option<u32> {
+    FINALS[1..]
+        .iter()
+        .position(|&f| f == c)
+        .map(|i| i as u32 + 1)
+}
+
+fn to_char(c: u32) -> char {
+    // Only ever called on values computed from the tables above.
+    core::char::from_u32(c).expect("sixbit::hangul")
+}
+
+// Splits each syllable into two or three jamo, passing anything else through.
+pub struct DecomposeHangul<I> {
+    inner: I,
+    pending: [char; 2],
+    npending: usize,
+}
+
+impl<I> DecomposeHangul<I>
+where
+    I: Iterator<Item = char>,
+{
+    pub fn new(inner: I) -> Self {
+        DecomposeHangul {
+            inner,
+            pending: ['\0'; 2],
+            npending: 0,
+        }
+    }
+}
+
+impl<I> Iterator for DecomposeHangul<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.npending > 0 {
+            let c = self.pending[0];
+            self.pending[0] = self.pending[1];
+            self.npending -= 1;
+            return Some(c);
+        }
+        let c = self.inner.next()?;
+        let s = c as u32;
+        if !(SYLLABLE_LO..=SYLLABLE_HI).contains(&s) {
+            return Some(c);
+        }
+        let s = s - SYLLABLE_LO;
+        let (l, v, t) = (s / (NVOWELS * NFINALS), s / NFINALS % NVOWELS, s % NFINALS);
+        self.pending[0] = to_char(VOWEL_LO + v);
+        self.npending = 1;
+        if t != 0 {
+            self.pending[1] = FINALS[t as usize];
+            self.npending = 2;
+        }
+        Some(INITIALS[l as usize])
+    }
+}
+
+// Reassembles syllables from jamo, passing anything else through.
+pub struct ComposeHangul<I> {
+    inner: I,
+    buf: [char; 4],
+    len: usize,
+}
+
+impl<I> ComposeHangul<I>
+where
+    I: Iterator<Item = char>,
+{
+    pub fn new(inner: I) -> Self {
+        ComposeHangul {
+            inner,
+            buf: ['\0'; 4],
+            len: 0,
+        }
+    }
+
+    fn peek(&mut self, i: usize) -> Option<char> {
+        while self.len <= i {
+            self.buf[self.len] = self.inner.next()?;
+            self.len += 1;
+        }
+        Some(self.buf[i])
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.buf.copy_within(n.., 0);
+        self.len -= n;
+    }
+}
+
+impl<I> Iterator for ComposeHangul<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        let l = initial_index(c);
+        let v = self.peek(1).and_then(vowel_index);
+        match (l, v) {
+            (Some(l), Some(v)) => {
+                let mut t = 0;
+                if let Some(f) = self.peek(2).and_then(final_index) {
+                    if self.peek(3).and_then(vowel_index).is_none() {
+                        t = f;
+                    }
+                }
+                self.consume(if t == 0 { 2 } else { 3 });
+                Some(to_char(SYLLABLE_LO + (l * NVOWELS + v) * NFINALS + t))
+            }
+            _ => {
+                self.consume(1);
+                Some(c)
+            }
+        }
+    }
+}
+
+pub fn encode_hangul<N>(s: &str) -> Result<N, EncodeError>
+where
+    N: PackedValue,
+{
+    encode(DecomposeHangul::new(s.chars()))
+}
+
+pub fn decode_hangul<N>(n: N) -> ComposeHangul<DecodeSixbitIter<N>>
+where
+    N: PackedValue,
+{
+    ComposeHangul::new(n.decode_sixbit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Page;
+
+    const WORDS: [&str; 16] = [
+        "가", "가나", "각", "값", "강", "닭", "사람", "사랑", "서울", "안녕", "없다", "읽다",
+        "한국", "한글", "해", "힣",
+    ];
+
+    #[test]
+    fn round_trip() {
+        for w in WORDS.iter() {
+            let n: u64 = encode_hangul(w).unwrap();
+            assert_eq!(decode_hangul(n).collect::<String>(), *w);
+        }
+        let jamo: String = DecomposeHangul::new("안녕".chars()).collect();
+        assert_eq!(jamo, "ㅇㅏㄴㄴㅕㅇ");
+        assert_eq!(encode_hangul::<u64>("안녕"), encode(jamo.chars()));
+
+        // Jamo that don't make syllables come back as they went in, and ones
+        // that do come back as syllables.
+        for (w, s) in [
+            ("ㅋㅋㅋ", "ㅋㅋㅋ"),
+            ("ㅏㅏ", "ㅏㅏ"),
+            ("ㄳ", "ㄳ"),
+            ("ㄸㅏㄸ", "따ㄸ"),
+        ]
+        .iter()
+        {
+            let n: u64 = encode_hangul(w).unwrap();
+            assert_eq!(decode_hangul(n).collect::<String>(), *s);
+        }
+
+        assert_eq!(
+            encode_hangul::<u64>("대한민국"),
+            Err(EncodeError::TooLong {
+                position: 10,
+                page: Page::HangulCompatibilityJamo,
+                needed: 11,
+                available: 10,
+                fits_in: Some(192),
+            })
+        );
+        assert_eq!(
+            encode_hangul::<u64>("한a"),
+            Err(EncodeError::MissingFromPage {
+                position: 3,
+                c: 'a',
+                page: Page::HangulCompatibilityJamo,
+            })
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let mut packed: Vec<u64> = WORDS.iter().map(|w| encode_hangul(w).unwrap()).collect();
+        packed.sort();
+        let sorted: Vec<String> = packed.iter().map(|&n| decode_hangul(n).collect()).collect();
+
+        // Packed order is the order of the jamo...
+        let mut jamo: Vec<String> = WORDS
+            .iter()
+            .map(|w| DecomposeHangul::new(w.chars()).collect())
+            .collect();
+        jamo.sort();
+        let recomposed: Vec<String> = jamo
+            .iter()
+            .map(|j| ComposeHangul::new(j.chars()).collect())
+            .collect();
+        assert_eq!(sorted, recomposed);
+
+        // ...which is string order apart from the final consonant caveat.
+        let mut expected = WORDS.to_vec();
+        expected.retain(|&w| w != "가나");
+        let mut without: Vec<&str> = sorted.iter().map(|s| s.as_str()).collect();
+        without.retain(|&w| w != "가나");
+        assert_eq!(without, expected);
+        assert!(encode_hangul::<u64>("각").unwrap() < encode_hangul("가나").unwrap());
+    }
+}
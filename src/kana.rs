@@ -0,0 +1,275 @@
+use core::iter::Peekable;
+
+use crate::{encode, DecodeSixbit, DecodeSixbitIter, EncodeError, PackedValue};
+
+// The kana page holds the halfwidth katakana, where voiced and semi-voiced
+// kana are written as the plain kana followed by a separate (semi-)voiced
+// sound mark, ﾞ or ﾟ. Japanese text is almost always in full-width katakana
+// or hiragana instead, so these map it onto the halfwidth forms on the way in,
+// and back to one or the other on the way out. Hiragana are the katakana
+// shifted down by 0x60, so they share a table.
+//
+// Kana with no halfwidth form (ヮ, ヰ, ヱ, ヵ, ヶ and friends) are passed
+// through unchanged, so they fail to encode as themselves.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KanaForm {
+    Katakana,
+    Hiragana,
+}
+
+const KATAKANA_LO: u32 = 0x30a1;
+const KATAKANA_HI: u32 = 0x30fc;
+const HIRAGANA_LO: u32 = 0x3041;
+const HIRAGANA_HI: u32 = 0x3096;
+const HIRAGANA_OFFSET: u32 = 0x60;
+
+// The halfwidth kana and sound mark for each full-width katakana from U+30A1
+// to U+30FC, or '\0' where there isn't one.
+#[rustfmt::skip]
+const KATAKANA: [(char, char); 92] = [
+    ('ｧ', '\0'), ('ｱ', '\0'), ('ｨ', '\0'), ('ｲ', '\0'), ('ｩ', '\0'), ('ｳ', '\0'), // ァ ア ィ イ ゥ ウ
+    ('ｪ', '\0'), ('ｴ', '\0'), ('ｫ', '\0'), ('ｵ', '\0'), ('ｶ', '\0'), ('ｶ', 'ﾞ'),  // ェ エ ォ オ カ ガ
+    ('ｷ', '\0'), ('ｷ', 'ﾞ'),  ('ｸ', '\0'), ('ｸ', 'ﾞ'),  ('ｹ', '\0'), ('ｹ', 'ﾞ'),  // キ ギ ク グ ケ ゲ
+    ('ｺ', '\0'), ('ｺ', 'ﾞ'),  ('ｻ', '\0'), ('ｻ', 'ﾞ'),  ('ｼ', '\0'), ('ｼ', 'ﾞ'),  // コ ゴ サ ザ シ ジ
+    ('ｽ', '\0'), ('ｽ', 'ﾞ'),  ('ｾ', '\0'), ('ｾ', 'ﾞ'),  ('ｿ', '\0'), ('ｿ', 'ﾞ'),  // ス ズ セ ゼ ソ ゾ
+    ('ﾀ', '\0'), ('ﾀ', 'ﾞ'),  ('ﾁ', '\0'), ('ﾁ', 'ﾞ'),  ('ｯ', '\0'), ('ﾂ', '\0'), // タ ダ チ ヂ ッ ツ
+    ('ﾂ', 'ﾞ'),  ('ﾃ', '\0'), ('ﾃ', 'ﾞ'),  ('ﾄ', '\0'), ('ﾄ', 'ﾞ'),  ('ﾅ', '\0'), // ヅ テ デ ト ド ナ
+    ('ﾆ', '\0'), ('ﾇ', '\0'), ('ﾈ', '\0'), ('ﾉ', '\0'), ('ﾊ', '\0'), ('ﾊ', 'ﾞ'),  // ニ ヌ ネ ノ ハ バ
+    ('ﾊ', 'ﾟ'),  ('ﾋ', '\0'), ('ﾋ', 'ﾞ'),  ('ﾋ', 'ﾟ'),  ('ﾌ', '\0'), ('ﾌ', 'ﾞ'),  // パ ヒ ビ ピ フ ブ
+    ('ﾌ', 'ﾟ'),  ('ﾍ', '\0'), ('ﾍ', 'ﾞ'),  ('ﾍ', 'ﾟ'),  ('ﾎ', '\0'), ('ﾎ', 'ﾞ'),  // プ ヘ ベ ペ ホ ボ
+    ('ﾎ', 'ﾟ'),  ('ﾏ', '\0'), ('ﾐ', '\0'), ('ﾑ', '\0'), ('ﾒ', '\0'), ('ﾓ', '\0'), // ポ マ ミ ム メ モ
+    ('ｬ', '\0'), ('ﾔ', '\0'), ('ｭ', '\0'), ('ﾕ', '\0'), ('ｮ', '\0'), ('ﾖ', '\0'), // ャ ヤ ュ ユ ョ ヨ
+    ('ﾗ', '\0'), ('ﾘ', '\0'), ('ﾙ', '\0'), ('ﾚ', '\0'), ('ﾛ', '\0'), ('\0', '\0'), // ラ リ ル レ ロ ヮ
+    ('ﾜ', '\0'), ('\0', '\0'), ('\0', '\0'), ('ｦ', '\0'), ('ﾝ', '\0'), ('ｳ', 'ﾞ'), // ワ ヰ ヱ ヲ ン ヴ
+    ('\0', '\0'), ('\0', '\0'), ('ﾜ', 'ﾞ'),  ('\0', '\0'), ('\0', '\0'), ('ｦ', 'ﾞ'), // ヵ ヶ ヷ ヸ ヹ ヺ
+    ('･', '\0'), ('ｰ', '\0'),                                                         // ・ ー
+];
+
+// Full-width punctuation and sound marks, with their halfwidth forms.
+const MARKS: [(char, char); 8] = [
+    ('、', '､'),
+    ('。', '｡'),
+    ('「', '｢'),
+    ('」', '｣'),
+    ('\u{3099}', 'ﾞ'),
+    ('\u{309a}', 'ﾟ'),
+    ('゛', 'ﾞ'),
+    ('゜', 'ﾟ'),
+];
+
+fn to_char(c: u32) -> char {
+    // Only ever called on values inside the kana blocks.
+    core::char::from_u32(c).expect("sixbit::kana")
+}
+
+// The halfwidth form of a character and the sound mark that follows it, or
+// the character itself if it has no halfwidth form.
+fn to_halfwidth(c: char) -> (char, char) {
+    let mut k = c as u32;
+    if (HIRAGANA_LO..=HIRAGANA_HI).contains(&k) {
+        k += HIRAGANA_OFFSET;
+    }
+    if (KATAKANA_LO..=KATAKANA_HI).contains(&k) {
+        match KATAKANA[(k - KATAKANA_LO) as usize] {
+            ('\0', _) => (c, '\0'),
+            pair => pair,
+        }
+    } else {
+        match MARKS.iter().find(|&&(f, _)| f == c) {
+            Some(&(_, h)) => (h, '\0'),
+            None => (c, '\0'),
+        }
+    }
+}
+
+// The full-width katakana for a halfwidth kana and sound mark, if there is one.
+fn to_fullwidth(h: char, mark: char) -> Option<char> {
+    KATAKANA
+        .iter()
+        .position(|&pair| pair == (h, mark))
+        .map(|i| to_char(KATAKANA_LO + i as u32))
+}
+
+// Maps full-width kana to halfwidth, splitting off sound marks.
+pub struct DecomposeKana<I> {
+    inner: I,
+    mark: char,
+}
+
+impl<I> DecomposeKana<I>
+where
+    I: Iterator<Item = char>,
+{
+    pub fn new(inner: I) -> Self {
+        DecomposeKana { inner, mark: '\0' }
+    }
+}
+
+impl<I> Iterator for DecomposeKana<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.mark != '\0' {
+            let m = self.mark;
+            self.mark = '\0';
+            return Some(m);
+        }
+        let (h, mark) = to_halfwidth(self.inner.next()?);
+        self.mark = mark;
+        Some(h)
+    }
+}
+
+// Maps halfwidth kana to full-width katakana or hiragana, joining sound marks
+// onto the kana before them where there's a voiced form to join them into.
+pub struct ComposeKana<I: Iterator<Item = char>> {
+    inner: Peekable<I>,
+    form: KanaForm,
+}
+
+impl<I> ComposeKana<I>
+where
+    I: Iterator<Item = char>,
+{
+    pub fn new(inner: I, form: KanaForm) -> Self {
+        ComposeKana {
+            inner: inner.peekable(),
+            form,
+        }
+    }
+}
+
+impl<I> Iterator for ComposeKana<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let h = self.inner.next()?;
+        let voiced = match self.inner.peek() {
+            Some(&m) if m == 'ﾞ' || m == 'ﾟ' => to_fullwidth(h, m),
+            _ => None,
+        };
+        let full = match voiced {
+            Some(v) => {
+                self.inner.next();
+                v
+            }
+            None => match to_fullwidth(h, '\0') {
+                Some(k) => k,
+                None => match MARKS.iter().rev().find(|&&(_, m)| m == h) {
+                    Some(&(f, _)) => f,
+                    None => h,
+                },
+            },
+        };
+        let k = full as u32;
+        // ー, ・ and the katakana-only ヷ and ヺ are shared by hiragana text.
+        if self.form == KanaForm::Hiragana
+            && (KATAKANA_LO..=HIRAGANA_HI + HIRAGANA_OFFSET).contains(&k)
+        {
+            Some(to_char(k - HIRAGANA_OFFSET))
+        } else {
+            Some(full)
+        }
+    }
+}
+
+pub fn encode_kana<N>(s: &str) -> Result<N, EncodeError>
+where
+    N: PackedValue,
+{
+    encode(DecomposeKana::new(s.chars()))
+}
+
+pub fn decode_kana<N>(n: N, form: KanaForm) -> ComposeKana<DecodeSixbitIter<N>>
+where
+    N: PackedValue,
+{
+    ComposeKana::new(n.decode_sixbit(), form)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Page;
+
+    fn round_trip(s: &str, form: KanaForm) -> String {
+        decode_kana(encode_kana::<u64>(s).unwrap(), form).collect()
+    }
+
+    #[test]
+    fn katakana() {
+        assert_eq!(encode_kana::<u64>("カタカナ"), encode("ｶﾀｶﾅ".chars()));
+        assert_eq!(encode_kana::<u64>("ガンダム"), encode("ｶﾞﾝﾀﾞﾑ".chars()));
+        assert_eq!(encode_kana::<u64>("パン"), encode("ﾊﾟﾝ".chars()));
+        assert_eq!(encode_kana::<u64>("ヴ"), encode("ｳﾞ".chars()));
+        for w in [
+            "カタカナ",
+            "ガンダム",
+            "パン",
+            "コーヒー",
+            "「ヴ」。",
+            "ヷヺ",
+        ]
+        .iter()
+        {
+            assert_eq!(round_trip(w, KanaForm::Katakana), *w);
+        }
+    }
+
+    #[test]
+    fn hiragana() {
+        assert_eq!(encode_kana::<u64>("ひらがな"), encode("ﾋﾗｶﾞﾅ".chars()));
+        for w in ["ひらがな", "ぱん", "らーめん", "ゔ", "きょう、"].iter() {
+            assert_eq!(round_trip(w, KanaForm::Hiragana), *w);
+        }
+        // Mixed input comes back in whichever form was asked for.
+        assert_eq!(round_trip("ひらガナ", KanaForm::Hiragana), "ひらがな");
+        assert_eq!(round_trip("ひらガナ", KanaForm::Katakana), "ヒラガナ");
+    }
+
+    #[test]
+    fn marks() {
+        // Combining and spacing sound marks both become halfwidth marks, and
+        // marks that can't be joined onto anything decode on their own.
+        assert_eq!(encode_kana::<u64>("か\u{3099}"), encode_kana("が"));
+        assert_eq!(encode_kana::<u64>("か゛"), encode_kana("が"));
+        assert_eq!(round_trip("ア゛", KanaForm::Katakana), "ア゛");
+        assert_eq!(round_trip("゜", KanaForm::Hiragana), "゜");
+    }
+
+    #[test]
+    fn unmapped() {
+        assert_eq!(
+            encode_kana::<u64>("ヰ"),
+            Err(EncodeError::NoCodePageFor {
+                position: 0,
+                c: 'ヰ',
+            })
+        );
+        assert_eq!(
+            encode_kana::<u64>("カヶ"),
+            Err(EncodeError::MissingFromPage {
+                position: 1,
+                c: 'ヶ',
+                page: Page::HalfwidthKana,
+            })
+        );
+        assert_eq!(
+            encode_kana::<u64>("ジャンボジェット"),
+            Err(EncodeError::TooLong {
+                position: 10,
+                page: Page::HalfwidthKana,
+                needed: 11,
+                available: 10,
+                fits_in: Some(192),
+            })
+        );
+    }
+}
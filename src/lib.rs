@@ -30,20 +30,115 @@
 //! so you can just do: `let enc = "hello".chars().encode_sixbit::<u64>()`. If
 //! there is a failure (say, the string spans pages or doesn't fit) you'll get
 //! back an `Err(EncodeError)` with details, otherwise `Ok(n)` where `n` is a
-//! `u64`.
+//! `u64`. The details are the position of the character that failed, the `Page`
+//! being used, and for strings that are too long, how long they are and the
+//! narrowest width that would hold them; it displays as a message and is a
+//! `std::error::Error` with the `std` feature.
+//!
+//! Strings that start out as UTF-8 bytes can be encoded with
+//! `encode_utf8::<u64>(b)`, which packs ASCII Latin strings 8 bytes at a time
+//! without decoding them to chars, and gives the same results as `encode`,
+//! though anything else costs a little more than `encode` does; `cargo bench`
+//! compares the two.
 //!
 //! Decoding is a `DecodeSixbitIter` iterator implementing `Iterator<char>`,
 //! attached to the various packed types, allowing you to write `let s:String =
 //! someu64.decode_sixbit().collect()`, or any other pattern that takes an
-//! `Iterator<char>`.
+//! `Iterator<char>`. The iterator knows its length up front and can be run from
+//! either end; `n.char_len()` gives the same length without decoding.
+//! `n.decode_utf8_into(&mut buf)` skips the chars and writes UTF-8 bytes
+//! straight from per-page tables, expanding Latin strings 16 codes at a time
+//! with SSSE3 where it's available, and `SixbitStr::from(n)` does that into a
+//! buffer on the stack that derefs to `&str`.
+//!
+//! If you'd rather not keep track of which integers are packed strings, the
+//! `Sixbit8` through `Sixbit128` wrappers can only be built by encoding or by
+//! validating a raw value, and display as the string they hold.
+//!
+//! Strings too long for a u128 can be packed into a `Wide<L>` of `L` u64 limbs,
+//! which encodes, decodes, validates, compares and casts like the integer
+//! widths do (`widen` takes any of them to `U192` or `U256`) and has
+//! `Sixbit192` and `Sixbit256` wrappers, though not `SixbitAny` variants or
+//! serde support; `U192` and `U256` hold 31 and 42 characters, and sort like
+//! the integers.
+//!
+//! When you don't know how long a string will be, `encode_smallest(s)` picks
+//! the narrowest width it fits in and returns a `SixbitAny`, which compares
+//! and hashes by the string it holds whatever its width.
+//!
+//! Latin, Greek and Cyrillic strings can be upper- or lowercased, and compared
+//! ignoring case, directly on the packed codes with `n.to_uppercase()`,
+//! `n.eq_ignore_case(m)` and so on.
+//!
+//! For NaN boxes, `encode_nanbox(s)` packs strings of up to 7 characters (or 2
+//! Chinese characters) into the payload of an `f64` NaN, `is_nanbox(f)` tells
+//! them apart from other doubles, and `decode_nanbox(f)` gets them back.
+//!
+//! For tagged machine words, `encode_reserving_low_bits::<u64>(s, k)` leaves
+//! the low `k` bits free, which `decode_reserving_low_bits(n, k)` ignores;
+//! `nchars_reserving_low_bits::<u64>(k)` says how many characters that leaves.
+//!
+//! Packed values can be cast between widths without decoding them, using
+//! `widen::<u32, u64>(v)` where that always works and `try_narrow::<u128,
+//! u32>(v)` where it might not.
+//!
+//! The `batch` module searches slices of `u64` or `u128` packed strings for
+//! equal values, prefixes, or a lower bound, using SIMD where it's available.
+//!
+//! With the `serde` feature enabled the wrappers serialize as strings in
+//! human-readable formats and as integers in binary ones, and raw packed fields
+//! can do the same with `#[serde(with = "sixbit::serde_packed")]`, which
+//! refuses to serialize values that wouldn't deserialize.
+//!
+//! String literals can also be encoded at compile time, with the
+//! `encode_str_u64` family of `const fn`s or with `sixbit!("hello")`, which
+//! fails to compile if the string doesn't encode.
+//!
+//! The decoding iterator is infallible: it will happily decode any integer,
+//! including ones this crate would never produce, where a zero code before the
+//! last character decodes to `'\0'`. If you are loading packed values from
+//! somewhere untrusted, call `validate(n)` or `n.try_decode_sixbit()` first,
+//! which check the value is exactly what `encode` would have produced and
+//! otherwise return a `DecodeError`, which displays and is an error the same
+//! way.
+//!
+//! For the common case of "inline if it fits, otherwise allocate",
+//! `SixbitString` holds any string in 16 bytes: packed into a `u128` if it
+//! encodes, or as a `Box<str>` if not. It compares, orders and hashes the same
+//! as the string it holds. `s.with_str(f)` reads it without allocating, where
+//! `s.as_str()` has to decode an inline string into a new `String`.
+//!
+//! `SymbolTable` and `SyncSymbolTable` intern names as `Symbol`s, which are
+//! the packed `u64` for any name that encodes in one and an index into a side
+//! table for the rest, so most names never touch the table at all.
+//!
+//! For whole columns of strings, `encode_all::<u64>(&strs)` gives a packed
+//! value for each string (zero for those that don't encode) and the index and
+//! error of each that doesn't, exactly as `encode` would, and
+//! `decode_all(&packed)` decodes them all into one `String` with the offset of
+//! each.
+//!
+//! The crate is `no_std` unless the default `std` feature is enabled, which
+//! adds runtime CPU feature detection for the `batch` module, the symbol
+//! tables, and the `alloc` feature it implies, which adds `SixbitString`,
+//! `encode_all` and `decode_all`. The `rayon` feature spreads those two over
+//! threads, with the same results. The `arbitrary` and `serde` features add
+//! support for those crates.
 //!
 //! In several cases you will need to normalize or decompose "standard" unicode
 //! text before pushing it through these interfaces. For example, the Hangul
-//! page only has compatibility jamo, so you have to decompose standard Korean
-//! text to that form before encoding. Similarly the Halfwidth Kana are unlikely
-//! to be the characters standard Japanese text arrives in, and Devanagari
-//! strings with nuktas will need to be decomposed before mapping. This crate
-//! does none of these tasks: it's a building block, not a complete solution.
+//! page only has compatibility jamo, so standard Korean text has to be split
+//! into jamo before encoding; `encode_hangul` and `decode_hangul` do that and
+//! put the syllables back together afterwards. Similarly the Halfwidth Kana are
+//! unlikely to be the characters standard Japanese text arrives in, so
+//! `encode_kana` maps hiragana and full-width katakana onto them, and
+//! `decode_kana` maps them back to either. Devanagari strings with nuktas will
+//! need to be decomposed before mapping. The `normalize` feature adds
+//! `encode_normalized`, which handles the Devanagari nukta consonants, Arabic
+//! presentation forms and letters with hamza or maddah, and Greek oxia and
+//! combining accents, and `decode_normalized`, which recomposes the output to
+//! NFC. The rest is up to you: this crate is a building block, not a complete
+//! solution.
 //!
 //! ## Code Pages
 //!
@@ -72,7 +167,9 @@
 //!
 //! We want to avoid wasting bits, and the number of spare bits in a packed
 //! value of N bits (modulo 6) varies, depending on its size: 2 spare bits for
-//! 8, 32 and 128-bit values; 4 spare bits for 16 and 64-bit values.
+//! 8, 32 and 128-bit values; 4 spare bits for 16 and 64-bit values. Wider
+//! values follow the same rule, except that a width with no spare bits, like
+//! 192, gives up one code for a 4-bit tag and 2 bits of zero padding.
 //!
 //! The tag for Chinese is allocated in all cases but there's only space for
 //! a nonzero sequence of the 15-bit codes in 32, 64 and 128-bit values, so
@@ -157,15 +254,24 @@
 //!
 //! | packed type | tag bits | coding bits | max 6-bit chars | max 15-bit chars |
 //! |-------------|----------|-------------|-----------------|------------------|
+//! | U256        | 4        | 252         | 42              | 16               |
+//! | U192        | 4        | 186         | 31              | 12               |
 //! | u128        | 2        | 126         | 21              | 8                |
 //! |  u64        | 4        |  60         | 10              | 4                |
 //! |  u32        | 2        |  30         |  5              | 2                |
 //! |  u16        | 4        |  12         |  2              | 0                |
 //! |   u8        | 2        |   6         |  1              | 0                |
 
-use std::mem::size_of;
-use std::ops::{BitOrAssign, ShlAssign};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
+use core::cmp::{min, Ordering};
+use core::fmt;
+use core::mem::size_of;
+use core::ops::{BitAnd, BitOrAssign, BitXor, Not, ShlAssign};
+
+#[cfg(feature = "arbitrary")]
 use arbitrary::Unstructured;
 
 #[rustfmt::skip]
@@ -407,20 +513,95 @@ pub(crate) const PAGES: [[char; 64]; 16] = [
 
 use consts::*;
 
+mod any;
+pub use any::{encode_smallest, SixbitAny, SixbitAnyChars};
+
+pub mod batch;
+
+mod case;
+
+mod cast;
+pub use cast::{try_narrow, widen, Widen};
+
+mod constant;
+#[doc(hidden)]
+pub use constant::__encode_str_or_panic;
+pub use constant::{
+    encode_str_u128, encode_str_u16, encode_str_u32, encode_str_u64, encode_str_u8,
+};
+
+mod hangul;
+pub use hangul::{decode_hangul, encode_hangul, ComposeHangul, DecomposeHangul};
+
+mod kana;
+pub use kana::{decode_kana, encode_kana, ComposeKana, DecomposeKana, KanaForm};
+
+mod lowbits;
+pub use lowbits::{
+    decode_reserving_low_bits, encode_reserving_low_bits, nchars_reserving_low_bits,
+    nwidechars_reserving_low_bits,
+};
+
+mod lookup;
+use lookup::lookup;
+
+mod nanbox;
+pub use nanbox::{decode_nanbox, encode_nanbox, is_nanbox};
+
+mod typed;
+pub use typed::{Sixbit, Sixbit128, Sixbit16, Sixbit192, Sixbit256, Sixbit32, Sixbit64, Sixbit8};
+
+mod utf8;
+pub use utf8::{encode_utf8, SixbitStr};
+
+mod wide;
+pub use wide::{Wide, U192, U256};
+
+#[cfg(feature = "normalize")]
+mod normalize;
+#[cfg(feature = "normalize")]
+pub use normalize::{decode_normalized, encode_normalized};
+
+#[cfg(feature = "alloc")]
+mod bulk;
+#[cfg(feature = "alloc")]
+pub use bulk::{decode_all, encode_all};
+
+#[cfg(feature = "alloc")]
+mod string;
+#[cfg(feature = "alloc")]
+pub use string::SixbitString;
+
+#[cfg(feature = "std")]
+mod symbol;
+#[cfg(feature = "std")]
+pub use symbol::{Symbol, SymbolTable, SyncSymbolTable};
+
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "serde")]
+pub use serde_impl::serde_packed;
+
 pub trait PackedValue
 where
     Self: Copy,
     Self: ShlAssign<usize>,
     Self: BitOrAssign<Self>,
-    Self: ::std::cmp::PartialOrd,
-    Self: ::std::fmt::Debug,
-    Self: ::std::fmt::LowerHex,
+    Self: BitAnd<Output = Self>,
+    Self: BitXor<Output = Self>,
+    Self: Not<Output = Self>,
+    Self: ::core::cmp::PartialOrd,
+    Self: ::core::fmt::Debug,
+    Self: ::core::fmt::LowerHex,
 {
     const NBITS: usize = size_of::<Self>() * 8;
     const NCHARS: usize = Self::NBITS / 6;
     const NTAGBITS: usize = Self::NBITS - (Self::NCHARS * 6);
-    const NCHARBITS: usize = Self::NBITS - Self::NTAGBITS;
-    const NWIDECHARS: usize = (Self::NBITS - Self::NTAGBITS) / 15;
+    const NCHARBITS: usize = Self::NCHARS * 6;
+    const NWIDECHARS: usize = Self::NCHARBITS / 15;
+    // Zero bits below the last code, in widths where the tag had to grow to
+    // make room (see `Wide`); none in the integer widths.
+    const NPADBITS: usize = Self::NBITS - Self::NTAGBITS - Self::NCHARBITS;
     // This is a bit ridiculous; I literally tried 4 different crates and every
     // trait I could find in the stdlib and it seems like there is some sort of
     // community-wide conspiracy to ensure the absence of generic truncating
@@ -430,40 +611,211 @@ where
     // This also seems somewhat contorted to express via existing traits.
     fn most_significant_byte(self) -> u8;
 
-    // This is to help generate random data in tests or fuzzers.
+    // These are inherent methods on every integer but not part of any trait.
+    fn leading_zeros(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+
+    // This is to help generate random data in tests or fuzzers. Every page
+    // usable at this width is picked, including Chinese in u8 and u16 where
+    // the only string it can hold is the empty one.
+    #[cfg(feature = "arbitrary")]
     fn arbitrary<'a>(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
         let page_num = if Self::NTAGBITS == 2 {
             u.int_in_range::<usize>(0..=3)? * 4
         } else {
             *u.choose::<usize>(&[0, 1, 2, 3, 4, 8, 11, 12, 15])?
         };
-        let mut chars: [char; 21] = ['\0'; 21];
-        if page_num == CHINESE_4BIT_TAG {
-            let len = u.int_in_range(0..=Self::NWIDECHARS)?;
-            for i in 0..len {
-                chars[i] = unsafe {
-                    char::from_u32_unchecked(
-                        u.int_in_range((CHINESE_LO as u32)..=(CHINESE_HI as u32))?,
-                    )
-                };
-            }
+        // Characters are picked as they're encoded, since wide values hold
+        // more of them than is worth buffering; the first error stops both.
+        let mut err = None;
+        let mut pick = |u: &mut Unstructured<'a>| -> Option<char> {
+            let c = if page_num == CHINESE_4BIT_TAG {
+                u.int_in_range((CHINESE_LO as u32)..=(CHINESE_HI as u32))
+                    .map(|i| unsafe { char::from_u32_unchecked(i) })
+            } else {
+                // Unassigned codes are all at the end of their page; pick only
+                // from the assigned ones, so that the last few before the holes
+                // are as likely as any other and strings don't get cut short.
+                let page = &PAGES[page_num];
+                let assigned = page.iter().position(|&c| c == '\u{ffff}').unwrap_or(64);
+                u.int_in_range(1..=assigned - 1).map(|i| page[i])
+            };
+            c.map_err(|e| err = Some(e)).ok()
+        };
+        let len = if page_num == CHINESE_4BIT_TAG {
+            u.int_in_range(0..=Self::NWIDECHARS)?
         } else {
-            let len = u.int_in_range(0..=Self::NCHARS)?;
-            for i in 0..len {
-                let ch = PAGES[page_num][u.int_in_range(1..=63)?];
-                if ch == '\u{ffff}' {
-                    break;
+            u.int_in_range(0..=Self::NCHARS)?
+        };
+        let packed = encode::<Self, _>((0..len).map_while(|_| pick(u)));
+        if let Some(e) = err {
+            return Err(e);
+        }
+        // This should always succeed. There's a bug if not.
+        Ok(packed.expect("sixbit::PackedValue::arbitrary"))
+    }
+
+    // Like `arbitrary`, but for fuzzing code that has to cope with values
+    // that aren't strings. Besides strings, it makes every kind of value that
+    // `validate` rejects: reserved tags (including Chinese where there's no
+    // room for a character), codes in the holes at the ends of pages, Chinese
+    // codes past the end of the URO, nonzero bits after the end of a string,
+    // and plain random bits.
+    #[cfg(feature = "arbitrary")]
+    fn arbitrary_raw<'a>(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let valid = Self::arbitrary(u)?;
+        let usable = |p: usize| Self::NTAGBITS == 4 || p & 0b11 == 0;
+        let mut pages = [0usize; 16];
+        let mut npages = 0;
+        match u.int_in_range(0..=5u8)? {
+            1 => {
+                for p in (0..16).filter(|&p| usable(p)) {
+                    let reserved = if p == CHINESE_4BIT_TAG {
+                        Self::NWIDECHARS == 0
+                    } else {
+                        PAGES[p][0] != '\0'
+                    };
+                    if reserved {
+                        pages[npages] = p;
+                        npages += 1;
+                    }
+                }
+                if npages > 0 {
+                    let page = *u.choose(&pages[..npages])?;
+                    let mut tag = !Self::truncating_cast_from(0);
+                    tag <<= Self::NBITS - Self::NTAGBITS;
+                    let codes = arbitrary_bits::<Self>(u)? & !tag;
+                    let mut out = pack_codes::<Self>(page, 6, 0, |_| Ok(0))?;
+                    out |= codes;
+                    return Ok(out);
                 }
-                chars[i] = ch;
             }
+            2 => {
+                for p in (0..16).filter(|&p| usable(p) && p != CHINESE_4BIT_TAG) {
+                    if PAGES[p][0] == '\0' && PAGES[p][63] == '\u{ffff}' {
+                        pages[npages] = p;
+                        npages += 1;
+                    }
+                }
+                if npages > 0 && Self::NCHARS > 0 {
+                    let page = *u.choose(&pages[..npages])?;
+                    let assigned = PAGES[page].iter().position(|&c| c == '\u{ffff}').unwrap();
+                    let at = u.int_in_range(0..=Self::NCHARS - 1)?;
+                    return pack_codes(page, 6, at + 1, |i| match i == at {
+                        true => u.int_in_range(assigned..=63),
+                        false => u.int_in_range(1..=assigned - 1),
+                    });
+                }
+            }
+            3 if Self::NWIDECHARS > 0 => {
+                let uro = (CHINESE_HI as usize) - (CHINESE_LO as usize) + 1;
+                let at = u.int_in_range(0..=Self::NWIDECHARS - 1)?;
+                return pack_codes(CHINESE_4BIT_TAG, 15, at + 1, |i| match i == at {
+                    true => u.int_in_range(uro + 1..=0x7fff),
+                    false => u.int_in_range(1..=uro),
+                });
+            }
+            4 => {
+                // A bit set past the terminator, or past the last code if
+                // there's no room for one; or failing that, a page claimed by
+                // the empty string.
+                let len = valid.char_len();
+                let (bits, max) = if tag_of(valid) == CHINESE_4BIT_TAG {
+                    (15, Self::NWIDECHARS)
+                } else {
+                    (6, Self::NCHARS)
+                };
+                let pad = Self::NBITS - Self::NTAGBITS - len * bits;
+                let room = if len == max {
+                    pad
+                } else {
+                    pad.saturating_sub(bits)
+                };
+                if room > 0 && u.arbitrary::<bool>()? {
+                    let mut bit = Self::truncating_cast_from(1);
+                    bit <<= u.int_in_range(0..=room - 1)?;
+                    let mut out = valid;
+                    out |= bit;
+                    return Ok(out);
+                }
+                return pack_codes(Page::Arabic.index(), 6, 0, |_| Ok(0));
+            }
+            5 => return arbitrary_bits(u),
+            _ => {}
         }
+        Ok(valid)
+    }
 
-        // This should always succeed. There's a bug if not.
-        Ok(
-            encode::<Self, _>(chars.iter().cloned().take_while(|x| *x != '\0'))
-                .expect("sixbit::PackedValue::arbitrary"),
+    // The number of characters in the string, found from the position of the
+    // last nonzero code rather than by decoding.
+    fn char_len(self) -> usize {
+        let tag = tag_of(self);
+        let (bits, max) = if tag == CHINESE_4BIT_TAG {
+            (15, Self::NWIDECHARS)
+        } else {
+            (6, Self::NCHARS)
+        };
+        let avail = Self::NBITS - Self::NTAGBITS;
+        let used = avail - min(self.trailing_zeros() as usize, avail);
+        min(used.div_ceil(bits), max)
+    }
+
+    // Since strings are left-aligned and zero-padded, prefix tests are just a
+    // masked comparison: keep the tag and the prefix's characters, and compare.
+    fn is_prefix_of(self, other: Self) -> bool {
+        other & prefix_mask(self) == self
+    }
+
+    fn common_prefix_len(self, other: Self) -> usize {
+        let zero = Self::truncating_cast_from(0);
+        if self == zero || other == zero {
+            return 0;
+        }
+        let tag = tag_of(self);
+        if tag != tag_of(other) {
+            return 0;
+        }
+        let diff = self ^ other;
+        if diff == zero {
+            return self.char_len();
+        }
+        // The first differing code is the first one past the common prefix,
+        // including when one string just stops: codes are never zero.
+        let same = diff.leading_zeros() as usize - Self::NTAGBITS;
+        min(
+            same / code_bits(tag),
+            min(self.char_len(), other.char_len()),
         )
     }
+
+    // The inclusive range of packed values holding strings that start with
+    // this one, for range scans over sorted packed values.
+    fn prefix_range(self) -> (Self, Self) {
+        let mut hi = self;
+        hi |= !prefix_mask(self);
+        (self, hi)
+    }
+
+    // Case conversion on the codes themselves, for the Latin, Greek and
+    // Cyrillic pages. None for other pages, or when lowercasing a string with
+    // a Σ or Ъ, which have no single lowercase code.
+    fn to_uppercase(self) -> Option<Self> {
+        case::to_uppercase(self)
+    }
+
+    fn to_lowercase(self) -> Option<Self> {
+        case::to_lowercase(self)
+    }
+
+    // Case-insensitive comparisons go by the uppercased strings, since that's
+    // defined for every string on a cased page.
+    fn eq_ignore_case(self, other: Self) -> Option<bool> {
+        Some(self.to_uppercase()? == other.to_uppercase()?)
+    }
+
+    fn cmp_ignore_case(self, other: Self) -> Option<Ordering> {
+        self.to_uppercase()?.partial_cmp(&other.to_uppercase()?)
+    }
 }
 
 impl PackedValue for u8 {
@@ -473,6 +825,12 @@ impl PackedValue for u8 {
     fn most_significant_byte(self) -> u8 {
         self
     }
+    fn leading_zeros(self) -> u32 {
+        u8::leading_zeros(self)
+    }
+    fn trailing_zeros(self) -> u32 {
+        u8::trailing_zeros(self)
+    }
 }
 
 impl PackedValue for u16 {
@@ -482,6 +840,12 @@ impl PackedValue for u16 {
     fn most_significant_byte(self) -> u8 {
         (self >> 8) as u8
     }
+    fn leading_zeros(self) -> u32 {
+        u16::leading_zeros(self)
+    }
+    fn trailing_zeros(self) -> u32 {
+        u16::trailing_zeros(self)
+    }
 }
 
 impl PackedValue for u32 {
@@ -491,6 +855,12 @@ impl PackedValue for u32 {
     fn most_significant_byte(self) -> u8 {
         (self >> 24) as u8
     }
+    fn leading_zeros(self) -> u32 {
+        u32::leading_zeros(self)
+    }
+    fn trailing_zeros(self) -> u32 {
+        u32::trailing_zeros(self)
+    }
 }
 
 impl PackedValue for u64 {
@@ -500,6 +870,12 @@ impl PackedValue for u64 {
     fn most_significant_byte(self) -> u8 {
         (self >> 56) as u8
     }
+    fn leading_zeros(self) -> u32 {
+        u64::leading_zeros(self)
+    }
+    fn trailing_zeros(self) -> u32 {
+        u64::trailing_zeros(self)
+    }
 }
 
 impl PackedValue for u128 {
@@ -509,14 +885,274 @@ impl PackedValue for u128 {
     fn most_significant_byte(self) -> u8 {
         (self >> 120) as u8
     }
+    fn leading_zeros(self) -> u32 {
+        u128::leading_zeros(self)
+    }
+    fn trailing_zeros(self) -> u32 {
+        u128::trailing_zeros(self)
+    }
+}
+
+// The code pages, numbered by their 4-bit tags.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Page {
+    Latin = 0,
+    Greek = 1,
+    Cyrillic = 2,
+    Hebrew = 3,
+    Arabic = 4,
+    Devanagari = 8,
+    HangulCompatibilityJamo = 11,
+    Chinese = 12,
+    HalfwidthKana = 15,
 }
 
-#[derive(PartialEq, Debug)]
+impl Page {
+    // Only ever called with the index of an assigned page.
+    const fn from_index(i: usize) -> Page {
+        match i {
+            0 => Page::Latin,
+            1 => Page::Greek,
+            2 => Page::Cyrillic,
+            3 => Page::Hebrew,
+            4 => Page::Arabic,
+            8 => Page::Devanagari,
+            11 => Page::HangulCompatibilityJamo,
+            12 => Page::Chinese,
+            15 => Page::HalfwidthKana,
+            _ => panic!("sixbit::Page: reserved page"),
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // Whether this page can be used at widths with only a 2-bit tag.
+    pub fn is_primary(self) -> bool {
+        self.index() & 0b11 == 0
+    }
+}
+
+impl fmt::Display for Page {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Page::Latin => "Latin",
+            Page::Greek => "Greek",
+            Page::Cyrillic => "Cyrillic",
+            Page::Hebrew => "Hebrew",
+            Page::Arabic => "Arabic",
+            Page::Devanagari => "Devanagari",
+            Page::HangulCompatibilityJamo => "Hangul Compatibility Jamo",
+            Page::Chinese => "Chinese",
+            Page::HalfwidthKana => "Halfwidth Kana",
+        })
+    }
+}
+
+// Positions count the chars given to the encoder, from zero. Functions that
+// rewrite their input before encoding it, like `encode_hangul`, report
+// positions in the rewritten chars.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum EncodeError {
-    TooLong,
-    NoCodePageFor(char),
-    PageUnavailable(usize),
-    MissingFromPage(char),
+    // The string has `needed` characters but there is only room for
+    // `available`, so the one at `position` is the first that doesn't fit.
+    // `fits_in` is the narrowest width, in bits, that the string would fit
+    // in, if there is one: 8, 16, 32, 64, 128, 192 or 256.
+    TooLong {
+        position: usize,
+        page: Page,
+        needed: usize,
+        available: usize,
+        fits_in: Option<usize>,
+    },
+    // The first character isn't in any page.
+    NoCodePageFor {
+        position: usize,
+        c: char,
+    },
+    // The first character's page needs a 4-bit tag, and this width only has
+    // a 2-bit one.
+    PageUnavailable {
+        position: usize,
+        page: Page,
+    },
+    // A character isn't in the page picked by the first one.
+    MissingFromPage {
+        position: usize,
+        c: char,
+        page: Page,
+    },
+    // Bytes given to `encode_utf8` that aren't UTF-8, after `position` good
+    // characters.
+    InvalidUtf8 {
+        position: usize,
+    },
+    // A value given to `try_narrow` that `validate` rejects, so there's no
+    // string to cast. Its position is always 0.
+    InvalidValue {
+        error: DecodeError,
+    },
+}
+
+impl EncodeError {
+    pub fn position(self) -> usize {
+        match self {
+            EncodeError::TooLong { position, .. }
+            | EncodeError::NoCodePageFor { position, .. }
+            | EncodeError::PageUnavailable { position, .. }
+            | EncodeError::MissingFromPage { position, .. }
+            | EncodeError::InvalidUtf8 { position } => position,
+            EncodeError::InvalidValue { .. } => 0,
+        }
+    }
+
+    pub fn page(self) -> Option<Page> {
+        match self {
+            EncodeError::TooLong { page, .. }
+            | EncodeError::PageUnavailable { page, .. }
+            | EncodeError::MissingFromPage { page, .. } => Some(page),
+            EncodeError::NoCodePageFor { .. }
+            | EncodeError::InvalidUtf8 { .. }
+            | EncodeError::InvalidValue { .. } => None,
+        }
+    }
+
+    const fn too_long(position: usize, page: usize, needed: usize, available: usize) -> Self {
+        EncodeError::TooLong {
+            position,
+            page: Page::from_index(page),
+            needed,
+            available,
+            fits_in: smallest_width(page, needed, 0),
+        }
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EncodeError::TooLong {
+                page,
+                needed,
+                available,
+                fits_in,
+                ..
+            } => {
+                write!(
+                    f,
+                    "{} {} characters don't fit in room for {}",
+                    needed, page, available
+                )?;
+                match fits_in {
+                    Some(bits) => write!(f, ", but would in {} bits", bits),
+                    None => f.write_str(", or in any width"),
+                }
+            }
+            EncodeError::NoCodePageFor { position, c } => {
+                write!(f, "no code page for {:?} at position {}", c, position)
+            }
+            EncodeError::PageUnavailable { page, .. } => {
+                write!(f, "the {} page isn't available at this width", page)
+            }
+            EncodeError::MissingFromPage { position, c, page } => {
+                write!(
+                    f,
+                    "{:?} at position {} isn't in the {} page",
+                    c, position, page
+                )
+            }
+            EncodeError::InvalidUtf8 { position } => {
+                write!(f, "invalid UTF-8 at position {}", position)
+            }
+            EncodeError::InvalidValue { error } => write!(f, "invalid packed value: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodeError::InvalidValue { error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Every width there is a PackedValue for, in bits, with its tag bits.
+const WIDTHS: [(usize, usize); 7] = [
+    (u8::NBITS, u8::NTAGBITS),
+    (u16::NBITS, u16::NTAGBITS),
+    (u32::NBITS, u32::NTAGBITS),
+    (u64::NBITS, u64::NTAGBITS),
+    (u128::NBITS, u128::NTAGBITS),
+    (U192::NBITS, U192::NTAGBITS),
+    (U256::NBITS, U256::NTAGBITS),
+];
+
+// The narrowest width that has room for `needed` characters from a page
+// above `reserved` low bits, if any does.
+const fn smallest_width(page: usize, needed: usize, reserved: usize) -> Option<usize> {
+    let bits = if page == CHINESE_4BIT_TAG { 15 } else { 6 };
+    let mut i = 0;
+    while i < WIDTHS.len() {
+        let (nbits, ntagbits) = WIDTHS[i];
+        let room = (nbits - ntagbits).saturating_sub(reserved) / bits;
+        if (ntagbits == 4 || page & 0b11 == 0) && needed <= room {
+            return Some(nbits);
+        }
+        i += 1;
+    }
+    None
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DecodeError {
+    // The tag selects a page that is reserved at this width.
+    ReservedTag(usize),
+    // A 6-bit code that has no character assigned in the selected page.
+    UnassignedCode(usize),
+    // Nonzero bits after the terminator, or a nonzero tag on an empty string.
+    NonCanonicalPadding,
+    // A 15-bit code that is past the end of the URO block.
+    ChineseOutOfRange(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::ReservedTag(tag) => {
+                write!(
+                    f,
+                    "tag {} selects a page that's reserved at this width",
+                    tag
+                )
+            }
+            DecodeError::UnassignedCode(code) => {
+                write!(f, "code {} has no character in its page", code)
+            }
+            DecodeError::NonCanonicalPadding => {
+                f.write_str("nonzero bits after the end of the string")
+            }
+            DecodeError::ChineseOutOfRange(code) => {
+                write!(f, "Chinese code {} is past the end of the URO block", code)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+// Look up a character in a page. The terminator and the '\u{ffff}'
+// placeholders in unassigned slots aren't in any: neither is a character we
+// can encode.
+const fn code_in_page(page: usize, c: char) -> Option<usize> {
+    match lookup(c) {
+        Some((p, code)) if p == page => Some(code),
+        _ => None,
+    }
 }
 
 fn chinese_15bit_delta(c: char) -> Option<usize> {
@@ -539,7 +1175,7 @@ where
         None => Ok(out),
         Some(&init) => {
             // First handle special case of Chinese characters, which are encoded as deltas.
-            if N::NCHARBITS > 0 && chinese_15bit_delta(init) != None {
+            if N::NCHARBITS > 0 && chinese_15bit_delta(init).is_some() {
                 let tag = if N::NTAGBITS == 2 {
                     CHINESE_2BIT_TAG
                 } else {
@@ -547,13 +1183,25 @@ where
                 };
                 out |= N::truncating_cast_from(tag);
                 let mut rembits: usize = N::NCHARBITS;
-                for c in pi {
+                let mut chars = pi.enumerate();
+                while let Some((pos, c)) = chars.next() {
                     if rembits < 15 {
-                        return Err(EncodeError::TooLong);
+                        // Count the rest, to say how long the string is.
+                        let needed = pos + 1 + chars.count();
+                        return Err(EncodeError::too_long(
+                            pos,
+                            CHINESE_4BIT_TAG,
+                            needed,
+                            N::NWIDECHARS,
+                        ));
                     }
                     match chinese_15bit_delta(c) {
                         None => {
-                            return Err(EncodeError::MissingFromPage(c));
+                            return Err(EncodeError::MissingFromPage {
+                                position: pos,
+                                c,
+                                page: Page::Chinese,
+                            });
                         }
                         Some(delta) => {
                             out <<= 15;
@@ -565,14 +1213,17 @@ where
                     }
                 }
                 // Pad remainder.
-                out <<= rembits;
+                out <<= rembits + N::NPADBITS;
                 return Ok(out);
             }
 
-            // Pick page: just try each one, there are only 16.
-            match PAGES.iter().position(|&p| p.binary_search(&init).is_ok()) {
+            // Pick page: the first character's.
+            match lookup(init).map(|(p, _)| p) {
                 // No page means this string won't work.
-                None => Err(EncodeError::NoCodePageFor(init)),
+                None => Err(EncodeError::NoCodePageFor {
+                    position: 0,
+                    c: init,
+                }),
                 Some(p) => {
                     let mut tag = p;
                     let mut rem: usize = N::NCHARS;
@@ -581,23 +1232,34 @@ where
                         // Tried a "secondary tag" when only
                         // using 2 tag bits, sorry!
                         if tag & 0b11 != 0 {
-                            return Err(EncodeError::PageUnavailable(tag));
+                            return Err(EncodeError::PageUnavailable {
+                                position: 0,
+                                page: Page::from_index(tag),
+                            });
                         }
                         tag >>= 2;
                     }
                     // Set tag.
                     out |= N::truncating_cast_from(tag);
                     // Encode chars.
-                    for c in pi {
+                    let mut chars = pi.enumerate();
+                    while let Some((pos, c)) = chars.next() {
                         if rem == 0 {
-                            // String is too long.
-                            return Err(EncodeError::TooLong);
+                            // String is too long; count the rest to say how long.
+                            let needed = pos + 1 + chars.count();
+                            return Err(EncodeError::too_long(pos, p, needed, N::NCHARS));
                         }
-                        match PAGES[p].binary_search(&c) {
+                        match code_in_page(p, c) {
                             // No code for c in page.
-                            Err(_) => return Err(EncodeError::MissingFromPage(c)),
+                            None => {
+                                return Err(EncodeError::MissingFromPage {
+                                    position: pos,
+                                    c,
+                                    page: Page::from_index(p),
+                                })
+                            }
                             // Got a code, use it!
-                            Ok(i) => {
+                            Some(i) => {
                                 out <<= 6;
                                 out |= N::truncating_cast_from(i);
                                 rem -= 1;
@@ -605,7 +1267,7 @@ where
                         }
                     }
                     // Pad remainder.
-                    out <<= 6 * rem;
+                    out <<= 6 * rem + N::NPADBITS;
                     Ok(out)
                 }
             }
@@ -613,6 +1275,39 @@ where
     }
 }
 
+fn code_bits(tag: usize) -> usize {
+    if tag == CHINESE_4BIT_TAG {
+        15
+    } else {
+        6
+    }
+}
+
+// The 4-bit page index of a packed value, whatever its tag width.
+fn tag_of<N: PackedValue>(n: N) -> usize {
+    let mut tag = n.most_significant_byte();
+    tag >>= 8 - N::NTAGBITS;
+    if N::NTAGBITS == 2 {
+        tag <<= 2;
+    }
+    tag as usize
+}
+
+// A mask covering the tag and characters of a packed value, such that masking
+// any other value with it leaves the prefix of that value of the same length.
+fn prefix_mask<N: PackedValue>(n: N) -> N {
+    let zero = N::truncating_cast_from(0);
+    // The empty string is a prefix of everything.
+    if n == zero {
+        return zero;
+    }
+    let bits = code_bits(tag_of(n));
+    let keep = N::NTAGBITS + n.char_len() * bits;
+    let mut mask = !zero;
+    mask <<= N::NBITS - keep;
+    mask
+}
+
 pub trait EncodeSixbit: Sized + Iterator<Item = char> {
     fn encode_sixbit<N: PackedValue>(self) -> Result<N, EncodeError>;
 }
@@ -627,48 +1322,93 @@ where
     }
 }
 
+// Decodes the `char_len()` characters of a packed value, from either end.
+// Values that don't validate decode to whatever their codes map to, which may
+// include '\0' for zero codes before the last nonzero one.
 pub struct DecodeSixbitIter<N: PackedValue> {
     tag: usize,
     tmp: N,
+    len: usize,
 }
 
-impl<N> Iterator for DecodeSixbitIter<N>
+impl<N> DecodeSixbitIter<N>
 where
     N: PackedValue,
 {
-    type Item = char;
-    fn next(self: &mut Self) -> Option<char> {
+    // Read the i'th remaining 6-bit or 15-bit code without consuming it.
+    fn code_at(&self, i: usize) -> usize {
+        let mut tmp = self.tmp;
+        tmp <<= i * code_bits(self.tag);
+        let hi = tmp.most_significant_byte() as usize;
         if self.tag == CHINESE_4BIT_TAG {
-            let ch0 = self.tmp.most_significant_byte();
-            match ch0 {
-                0 => None,
-                i => {
-                    self.tmp <<= 8;
-                    let ch1 = self.tmp.most_significant_byte();
-                    self.tmp <<= 7;
-                    let delta = ((i as u32) << 7) | ((ch1 as u32) >> 1);
-                    char::from_u32((CHINESE_LO as u32) + delta - 1)
-                }
-            }
+            tmp <<= 8;
+            let lo = tmp.most_significant_byte() as usize;
+            (hi << 7) | (lo >> 1)
         } else {
-            let mut ch = self.tmp.most_significant_byte();
-            ch >>= 2;
-            match ch {
-                0 => None,
-                i => {
-                    self.tmp <<= 6;
-                    Some(PAGES[self.tag][i as usize])
-                }
+            hi >> 2
+        }
+    }
+
+    fn peek_code(&self) -> usize {
+        self.code_at(0)
+    }
+
+    fn char_of(&self, code: usize) -> Option<char> {
+        match code {
+            0 => Some('\0'),
+            i if self.tag == CHINESE_4BIT_TAG => {
+                char::from_u32((CHINESE_LO as u32) + (i as u32) - 1)
             }
+            i => Some(PAGES[self.tag][i]),
         }
     }
 }
 
+impl<N> Iterator for DecodeSixbitIter<N>
+where
+    N: PackedValue,
+{
+    type Item = char;
+    fn next(&mut self) -> Option<char> {
+        if self.len == 0 {
+            return None;
+        }
+        let code = self.peek_code();
+        self.tmp <<= code_bits(self.tag);
+        self.len -= 1;
+        self.char_of(code)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<N> DoubleEndedIterator for DecodeSixbitIter<N>
+where
+    N: PackedValue,
+{
+    fn next_back(&mut self) -> Option<char> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        self.char_of(self.code_at(self.len))
+    }
+}
+
+impl<N> ExactSizeIterator for DecodeSixbitIter<N> where N: PackedValue {}
+
 pub trait DecodeSixbit
 where
     Self: PackedValue,
 {
     fn decode_sixbit(self) -> DecodeSixbitIter<Self>;
+    fn try_decode_sixbit(self) -> Result<DecodeSixbitIter<Self>, DecodeError>;
+    // Write the string as UTF-8 and return its length in bytes. Bytes past
+    // that length may be overwritten too, when there's room for them. Panics
+    // if `out` is too short; 3 bytes per character always does.
+    fn decode_utf8_into(self, out: &mut [u8]) -> usize;
 }
 
 impl<N> DecodeSixbit for N
@@ -677,24 +1417,103 @@ where
 {
     fn decode_sixbit(self) -> DecodeSixbitIter<Self> {
         let mut tmp = self;
-        let mut tag = self.most_significant_byte();
-        tag >>= 8 - N::NTAGBITS;
-        if N::NTAGBITS == 2 {
-            tag <<= 2;
-        }
         tmp <<= N::NTAGBITS;
         DecodeSixbitIter {
-            tag: tag as usize,
+            tag: tag_of(self),
             tmp,
+            len: self.char_len(),
         }
     }
+
+    fn try_decode_sixbit(self) -> Result<DecodeSixbitIter<Self>, DecodeError> {
+        validate(self)?;
+        Ok(self.decode_sixbit())
+    }
+
+    fn decode_utf8_into(self, out: &mut [u8]) -> usize {
+        utf8::decode_utf8_into(self, out)
+    }
+}
+
+// `count` codes of `bits` bits each, from `code`, after the tag of `page`.
+#[cfg(feature = "arbitrary")]
+fn pack_codes<N: PackedValue>(
+    page: usize,
+    bits: usize,
+    count: usize,
+    mut code: impl FnMut(usize) -> arbitrary::Result<usize>,
+) -> arbitrary::Result<N> {
+    let tag = if N::NTAGBITS == 2 { page >> 2 } else { page };
+    let mut out = N::truncating_cast_from(tag);
+    for i in 0..count {
+        out <<= bits;
+        out |= N::truncating_cast_from(code(i)?);
+    }
+    out <<= N::NBITS - N::NTAGBITS - count * bits;
+    Ok(out)
+}
+
+#[cfg(feature = "arbitrary")]
+fn arbitrary_bits<N: PackedValue>(u: &mut Unstructured<'_>) -> arbitrary::Result<N> {
+    let mut out = N::truncating_cast_from(0);
+    for i in 0..N::NBITS / 8 {
+        // Not before the first byte, since a u8 can't be shifted by 8.
+        if i > 0 {
+            out <<= 8;
+        }
+        out |= N::truncating_cast_from(u.arbitrary::<u8>()? as usize);
+    }
+    Ok(out)
+}
+
+// Check that a packed value is exactly what `encode` would produce for some
+// string, so that decoding it yields only real characters and re-encoding the
+// result gives back the same value.
+pub fn validate<N>(n: N) -> Result<(), DecodeError>
+where
+    N: PackedValue,
+{
+    let mut it = n.decode_sixbit();
+    let zero = N::truncating_cast_from(0);
+    let (bits, max) = if it.tag == CHINESE_4BIT_TAG {
+        if N::NWIDECHARS == 0 {
+            // Chinese tag in a value too small to hold a character.
+            return Err(DecodeError::ReservedTag(it.tag));
+        }
+        (15, N::NWIDECHARS)
+    } else {
+        if PAGES[it.tag][0] != '\0' {
+            return Err(DecodeError::ReservedTag(it.tag));
+        }
+        (6, N::NCHARS)
+    };
+    let mut len = 0;
+    while len < max {
+        let code = it.peek_code();
+        if code == 0 {
+            break;
+        }
+        if bits == 15 {
+            if code > (CHINESE_HI as usize) - (CHINESE_LO as usize) + 1 {
+                return Err(DecodeError::ChineseOutOfRange(code));
+            }
+        } else if PAGES[it.tag][code] == '\u{ffff}' {
+            return Err(DecodeError::UnassignedCode(code));
+        }
+        it.tmp <<= bits;
+        len += 1;
+    }
+    // Everything past the terminator must be zero, and the empty string is
+    // always encoded as plain zero, whatever page it might have claimed.
+    if it.tmp != zero || (len == 0 && n != zero) {
+        return Err(DecodeError::NonCanonicalPadding);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
 
-    use rand::RngCore;
-
     use super::*;
     #[test]
     fn misc_invariants() {
@@ -760,20 +1579,215 @@ mod tests {
         assert!(round_trip::<u8>("").is_ok());
 
         // Error conditions: TooLong.
-        assert!(round_trip::<u128>("PRINTER_FULLY_OPERATIONAL") == Err(EncodeError::TooLong));
-        assert!(round_trip::<u64>("ATDT_123_4567") == Err(EncodeError::TooLong));
-        assert!(round_trip::<u32>("aaaaaaa") == Err(EncodeError::TooLong));
-        assert!(round_trip::<u16>("aba") == Err(EncodeError::TooLong));
-        assert!(round_trip::<u8>("OOH") == Err(EncodeError::TooLong));
+        let too_long = |needed, available, fits_in| EncodeError::TooLong {
+            position: available,
+            page: Page::Latin,
+            needed,
+            available,
+            fits_in,
+        };
+        assert!(
+            round_trip::<u128>("PRINTER_FULLY_OPERATIONAL") == Err(too_long(25, 21, Some(192)))
+        );
+        assert!(round_trip::<u64>("ATDT_123_4567") == Err(too_long(13, 10, Some(128))));
+        assert!(round_trip::<u32>("aaaaaaa") == Err(too_long(7, 5, Some(64))));
+        assert!(round_trip::<u16>("aba") == Err(too_long(3, 2, Some(32))));
+        assert!(round_trip::<u8>("OOH") == Err(too_long(3, 1, Some(32))));
+        let huge = "a".repeat(43);
+        assert!(round_trip::<u64>(&huge) == Err(too_long(43, 10, None)));
 
         // Error conditions: NoCodePageFor.
-        assert!(round_trip::<u128>("©2018") == Err(EncodeError::NoCodePageFor('©')));
+        let no_page = |c| EncodeError::NoCodePageFor { position: 0, c };
+        assert!(round_trip::<u128>("©2018") == Err(no_page('©')));
 
         // Error conditions: PageUnavailable.
-        assert!(round_trip::<u128>("ΨΩ") == Err(EncodeError::PageUnavailable(1)));
+        assert!(
+            round_trip::<u128>("ΨΩ")
+                == Err(EncodeError::PageUnavailable {
+                    position: 0,
+                    page: Page::Greek
+                })
+        );
 
         // Error conditions: MissingFromPage.
-        assert!(round_trip::<u64>("sh@rk") == Err(EncodeError::MissingFromPage('@')));
+        let missing = |position, c| EncodeError::MissingFromPage {
+            position,
+            c,
+            page: Page::Latin,
+        };
+        assert!(round_trip::<u64>("sh@rk") == Err(missing(2, '@')));
+
+        // The terminator and the unassigned-slot placeholder are not codes.
+        assert!(round_trip::<u64>("\0") == Err(no_page('\0')));
+        assert!(round_trip::<u64>("a\0") == Err(missing(1, '\0')));
+        assert!(round_trip::<u64>("\u{ffff}") == Err(no_page('\u{ffff}')));
+    }
+
+    #[test]
+    fn error_details() {
+        fn err<N: PackedValue>(s: &str) -> EncodeError {
+            s.chars().encode_sixbit::<N>().unwrap_err()
+        }
+        let e = err::<u64>("高速火车站");
+        assert_eq!(e.position(), 4);
+        assert_eq!(e.page(), Some(Page::Chinese));
+        assert_eq!(
+            e.to_string(),
+            "5 Chinese characters don't fit in room for 4, but would in 128 bits"
+        );
+        assert_eq!(
+            err::<u32>("αβ").to_string(),
+            "the Greek page isn't available at this width"
+        );
+        let greek = "αβγδεζηθικλμνξοπρστυφχψω".repeat(2);
+        assert_eq!(err::<u64>(&greek).page(), Some(Page::Greek));
+        assert_eq!(
+            err::<u64>(&greek).to_string(),
+            "48 Greek characters don't fit in room for 10, or in any width"
+        );
+        assert_eq!(
+            err::<u64>("ab€").to_string(),
+            "'€' at position 2 isn't in the Latin page"
+        );
+        let e = err::<u64>("€");
+        assert_eq!(e.to_string(), "no code page for '€' at position 0");
+        assert_eq!(e.page(), None);
+
+        // It works with `?` into boxed errors.
+        #[cfg(feature = "std")]
+        fn boxed() -> Result<u64, Box<dyn std::error::Error>> {
+            Ok("sh@rk".chars().encode_sixbit()?)
+        }
+        #[cfg(feature = "std")]
+        assert_eq!(
+            boxed().unwrap_err().to_string(),
+            "'@' at position 2 isn't in the Latin page"
+        );
+    }
+
+    #[test]
+    fn prefixes() {
+        fn enc<N: PackedValue>(s: &str) -> N {
+            s.chars().encode_sixbit::<N>().unwrap()
+        }
+        fn check<N: PackedValue>(a: &str, b: &str) {
+            let (pa, pb) = (enc::<N>(a), enc::<N>(b));
+            let common = a.chars().zip(b.chars()).take_while(|(x, y)| x == y);
+            assert_eq!(pa.is_prefix_of(pb), b.starts_with(a));
+            assert_eq!(pb.is_prefix_of(pa), a.starts_with(b));
+            assert_eq!(pa.common_prefix_len(pb), common.count());
+            let (lo, hi) = pa.prefix_range();
+            assert_eq!(lo <= pb && pb <= hi, b.starts_with(a));
+        }
+        check::<u128>("Printer", "Printer_Working");
+        check::<u128>("Printer_Working", "Printer_Waiting");
+        check::<u64>("", "abc");
+        check::<u64>("abc", "abc");
+        check::<u64>("abc", "xyz");
+        check::<u64>("abc", "αβγ");
+        check::<u64>("αβ", "αβγ");
+        check::<u64>("合伙", "合伙人");
+        check::<u64>("合伙人", "合作");
+        check::<u64>("一", "一丁");
+        check::<u32>("同", "同事");
+        check::<u16>("γ", "γη");
+        check::<u8>("", "و");
+    }
+
+    #[test]
+    fn lengths() {
+        for s in &["", "a", "NO_CARRIER", "αλήθεια", "ｲｸﾂｶﾉ", "合伙人", "一"] {
+            let enc = round_trip::<u64>(s).unwrap();
+            let n = s.chars().count();
+            assert_eq!(enc.char_len(), n);
+            assert_eq!(enc.decode_sixbit().len(), n);
+            let rev: String = enc.decode_sixbit().rev().collect();
+            assert_eq!(rev, s.chars().rev().collect::<String>());
+        }
+        let full: u128 = "高速火车站再见一".chars().encode_sixbit().unwrap();
+        assert_eq!(full.char_len(), 8);
+        let max: u128 = "PRINTER_is_on_FIRE_ok".chars().encode_sixbit().unwrap();
+        assert_eq!(max.char_len(), 21);
+
+        // Both ends at once.
+        let mut it = "abcde"
+            .chars()
+            .encode_sixbit::<u32>()
+            .unwrap()
+            .decode_sixbit();
+        assert_eq!(it.next(), Some('a'));
+        assert_eq!(it.next_back(), Some('e'));
+        assert_eq!(it.len(), 3);
+        assert_eq!(it.collect::<String>(), "bcd");
+
+        // A zero code in the middle of a value that doesn't validate decodes
+        // to '\0' rather than ending the string, so the length is still known
+        // without decoding.
+        let gap: u32 = 38 << 24 | 39 << 12;
+        assert_eq!(validate(gap), Err(DecodeError::NonCanonicalPadding));
+        assert_eq!(gap.char_len(), 3);
+        assert_eq!(gap.decode_sixbit().collect::<String>(), "a\0b");
+        assert_eq!(gap.decode_sixbit().rev().collect::<String>(), "b\0a");
+        let mut buf = [0u8; 15];
+        let len = gap.decode_utf8_into(&mut buf);
+        assert_eq!(&buf[..len], b"a\0b");
+    }
+
+    #[test]
+    fn validation() {
+        // Everything the encoder produces validates.
+        for s in &[
+            "",
+            "NO_CARRIER",
+            "αλήθεια",
+            "לעשות",
+            "ㅇㅜㅁ",
+            "ｲｸﾂｶﾉ",
+            "合伙人",
+            "一丁",
+        ] {
+            let enc = round_trip::<u64>(s).unwrap();
+            assert_eq!(validate(enc), Ok(()));
+            let dec: String = enc.try_decode_sixbit().unwrap().collect();
+            assert_eq!(&dec, s);
+        }
+
+        // Reserved pages, and the Chinese tag where there is no room for it.
+        assert_eq!(validate(0x5u64 << 60), Err(DecodeError::ReservedTag(5)));
+        assert_eq!(validate(0xdu16 << 12), Err(DecodeError::ReservedTag(13)));
+        assert_eq!(validate(0xc0u8), Err(DecodeError::ReservedTag(12)));
+        assert!(0xc0u8.try_decode_sixbit().is_err());
+
+        // Holes at the end of the Hebrew page.
+        let hole = (0x3u64 << 60) | (60 << 54);
+        assert_eq!(validate(hole), Err(DecodeError::UnassignedCode(60)));
+
+        // Junk after the terminator, or a tag with nothing after it.
+        let abc: u64 = "abc".chars().encode_sixbit().unwrap();
+        assert_eq!(validate(abc | 1), Err(DecodeError::NonCanonicalPadding));
+        assert_eq!(
+            validate(0x1u64 << 60),
+            Err(DecodeError::NonCanonicalPadding)
+        );
+        let wide: u128 = "高速火车站".chars().encode_sixbit().unwrap();
+        assert_eq!(validate(wide | 1), Err(DecodeError::NonCanonicalPadding));
+
+        // 15-bit codes past U+9FFF.
+        let far = (0x3u32 << 30) | (0x7fff << 15);
+        assert_eq!(validate(far), Err(DecodeError::ChineseOutOfRange(0x7fff)));
+
+        assert_eq!(
+            DecodeError::ReservedTag(5).to_string(),
+            "tag 5 selects a page that's reserved at this width"
+        );
+        assert_eq!(
+            validate(hole).unwrap_err().to_string(),
+            "code 60 has no character in its page"
+        );
+        assert_eq!(
+            validate(abc | 1).unwrap_err().to_string(),
+            "nonzero bits after the end of the string"
+        );
     }
 
     fn check_order<N: PackedValue>(a: &str, b: &str) {
@@ -869,14 +1883,106 @@ mod tests {
         assert!(round_trip::<u16>("ﾔﾙ").is_ok());
     }
 
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary_coverage() {
+        use rand::{RngCore, SeedableRng};
+        fn check<N: PackedValue>(rng: &mut rand::rngs::StdRng) {
+            let mut seen = [[false; 64]; 16];
+            let mut bytes = [0u8; 1 << 16];
+            rng.fill_bytes(&mut bytes);
+            let mut u = arbitrary::Unstructured::new(&bytes);
+            while let Ok(packed) = <N as PackedValue>::arbitrary(&mut u) {
+                assert_eq!(validate(packed), Ok(()));
+                let mut it = packed.decode_sixbit();
+                while it.len() > 0 {
+                    seen[it.tag][it.peek_code() & 63] = true;
+                    it.next();
+                }
+                if u.is_empty() {
+                    break;
+                }
+            }
+            // The last assigned code in every page turns up.
+            let pages: &[usize] = if N::NTAGBITS == 2 {
+                &[0, 4, 8]
+            } else {
+                &[0, 1, 2, 3, 4, 8, 11, 15]
+            };
+            for &p in pages {
+                let last = PAGES[p].iter().rposition(|&c| c != '\u{ffff}').unwrap();
+                assert!(seen[p][last], "page {} code {}", p, last);
+            }
+        }
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x5157b17);
+        check::<u16>(&mut rng);
+        check::<u64>(&mut rng);
+        check::<u128>(&mut rng);
+        check::<U192>(&mut rng);
+
+        // Raw values cover every way of not being a string, and decode
+        // without panicking whatever they are.
+        fn check_raw<N: PackedValue>(rng: &mut rand::rngs::StdRng, seen: &mut [bool; 5]) {
+            let mut bytes = [0u8; 1 << 16];
+            rng.fill_bytes(&mut bytes);
+            let mut u = arbitrary::Unstructured::new(&bytes);
+            let mut buf = vec![0u8; 3 * N::NCHARS];
+            while let Ok(packed) = <N as PackedValue>::arbitrary_raw(&mut u) {
+                let kind = match validate(packed) {
+                    Ok(()) => 0,
+                    Err(DecodeError::ReservedTag(_)) => 1,
+                    Err(DecodeError::UnassignedCode(_)) => 2,
+                    Err(DecodeError::ChineseOutOfRange(_)) => 3,
+                    Err(DecodeError::NonCanonicalPadding) => 4,
+                };
+                seen[kind] = true;
+                let s: String = packed.decode_sixbit().collect();
+                let len = packed.decode_utf8_into(&mut buf);
+                assert_eq!(&buf[..len], s.as_bytes());
+                if u.is_empty() {
+                    break;
+                }
+            }
+        }
+        let mut seen = [false; 5];
+        check_raw::<u8>(&mut rng, &mut seen);
+        // The Chinese tag with no room for a character.
+        assert!(seen[1]);
+        check_raw::<u16>(&mut rng, &mut seen);
+        check_raw::<u32>(&mut rng, &mut seen);
+        check_raw::<u64>(&mut rng, &mut seen);
+        check_raw::<u128>(&mut rng, &mut seen);
+        check_raw::<U192>(&mut rng, &mut seen);
+        assert_eq!(seen, [true; 5]);
+
+        // Each width on its own, for the errors it can have.
+        let only = |f: fn(&mut rand::rngs::StdRng, &mut [bool; 5]), rng: &mut _| {
+            let mut seen = [false; 5];
+            f(rng, &mut seen);
+            seen
+        };
+        assert_eq!(
+            only(check_raw::<u16>, &mut rng),
+            [true, true, true, false, true]
+        );
+        assert_eq!(
+            only(check_raw::<u32>, &mut rng),
+            [true, false, true, true, true]
+        );
+        assert_eq!(only(check_raw::<u64>, &mut rng), [true; 5]);
+    }
+
+    #[cfg(feature = "arbitrary")]
     #[test]
     fn test_arbitrary() {
+        use rand::RngCore;
         for _ in 0..64 {
             let mut bytes = [0u8; 1024];
             rand::thread_rng().fill_bytes(&mut bytes);
             let mut u = arbitrary::Unstructured::new(&bytes);
             let packed = <u128 as PackedValue>::arbitrary(&mut u).expect("arbitrary");
             dbg!(packed);
+            assert_eq!(validate(packed), Ok(()));
             let decoded: String = packed.decode_sixbit().collect();
             dbg!(decoded);
         }
@@ -0,0 +1,111 @@
+use crate::PAGES;
+
+// A reverse index of the pages: the page and code of every character that's
+// in one, in two array lookups instead of a binary search of each page.
+//
+// Every paged character is in the BMP, so a character's top 9 bits pick one
+// of 512 blocks of 128 characters, and its low 7 bits an entry in the block.
+// Most blocks have nothing in any page, and share the empty block 0; the rest
+// get a block of entries of their own, holding `page << 6 | code`, or 0 for
+// characters in no page, since no character has code 0. It's all built at
+// compile time from PAGES, so there's nothing to keep in sync.
+
+const NBLOCKS: usize = count_blocks();
+const BLOCK_OF: [u8; 512] = block_of();
+const ENTRIES: [[u16; 128]; NBLOCKS] = entries();
+
+// Run the body for every character in every page, with its page and code.
+macro_rules! each_paged_char {
+    (|$p:ident, $code:ident, $c:ident| $body:block) => {
+        let mut $p = 0;
+        while $p < PAGES.len() {
+            let mut $code = 1;
+            while $code < 64 {
+                let $c = PAGES[$p][$code] as u32;
+                if $c != 0xffff {
+                    assert!($c <= 0xffff, "sixbit: paged character outside the BMP");
+                    $body
+                }
+                $code += 1;
+            }
+            $p += 1;
+        }
+    };
+}
+
+const fn block_of() -> [u8; 512] {
+    let mut block_of = [0u8; 512];
+    let mut next = 1;
+    each_paged_char!(|_p, _code, c| {
+        if block_of[(c >> 7) as usize] == 0 {
+            block_of[(c >> 7) as usize] = next;
+            next += 1;
+        }
+    });
+    block_of
+}
+
+const fn count_blocks() -> usize {
+    let block_of = block_of();
+    let mut n = 0;
+    let mut i = 0;
+    while i < block_of.len() {
+        if block_of[i] as usize > n {
+            n = block_of[i] as usize;
+        }
+        i += 1;
+    }
+    n + 1
+}
+
+const fn entries() -> [[u16; 128]; NBLOCKS] {
+    let mut entries = [[0u16; 128]; NBLOCKS];
+    each_paged_char!(|p, code, c| {
+        let (b, i) = (BLOCK_OF[(c >> 7) as usize] as usize, (c & 127) as usize);
+        // A character in two pages would only be found in the first.
+        assert!(entries[b][i] == 0, "sixbit: character in two pages");
+        entries[b][i] = (p << 6 | code) as u16;
+    });
+    entries
+}
+
+// The page and code of a character, if it's in a page. Chinese characters
+// aren't: they're their own code.
+pub(crate) const fn lookup(c: char) -> Option<(usize, usize)> {
+    let c = c as u32;
+    if c > 0xffff {
+        return None;
+    }
+    // Borrowed, so they're promoted to statics rather than copied out.
+    let (block_of, entries) = (&BLOCK_OF, &ENTRIES);
+    match entries[block_of[(c >> 7) as usize] as usize][(c & 127) as usize] {
+        0 => None,
+        e => Some(((e >> 6) as usize, (e & 63) as usize)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What `encode` used to do: search each page in turn.
+    fn search(c: char) -> Option<(usize, usize)> {
+        PAGES
+            .iter()
+            .enumerate()
+            .find_map(|(p, page)| match page.binary_search(&c) {
+                Ok(i) if i != 0 && c != '\u{ffff}' => Some((p, i)),
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn every_char() {
+        for c in (0..=0x10ffff).filter_map(char::from_u32) {
+            assert_eq!(lookup(c), search(c), "{:?}", c);
+        }
+        // A fraction of the 128KB a flat table of the BMP would take.
+        let size = core::mem::size_of_val(&BLOCK_OF) + core::mem::size_of_val(&ENTRIES);
+        assert!(size <= 4096, "{}", size);
+    }
+}
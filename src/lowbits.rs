@@ -0,0 +1,189 @@
+use crate::{
+    encode, smallest_width, tag_of, DecodeSixbit, DecodeSixbitIter, EncodeError, PackedValue, Page,
+    CHINESE_4BIT_TAG,
+};
+
+// Packed strings that leave their `k` low bits free, for machine words that
+// keep a tag of their own there (pointer tagging, usually). Strings are
+// already left-aligned and zero-padded, so this is ordinary encoding with
+// fewer characters allowed: however many whole codes fit above the reserved
+// bits. Whatever is put in the low bits afterwards can only affect the order
+// of words holding the same string.
+
+pub fn nchars_reserving_low_bits<N: PackedValue>(k: usize) -> usize {
+    (N::NCHARBITS + N::NPADBITS).saturating_sub(k) / 6
+}
+
+pub fn nwidechars_reserving_low_bits<N: PackedValue>(k: usize) -> usize {
+    (N::NCHARBITS + N::NPADBITS).saturating_sub(k) / 15
+}
+
+pub fn encode_reserving_low_bits<N: PackedValue>(s: &str, k: usize) -> Result<N, EncodeError> {
+    let (n, tag, needed) = match encode::<N, _>(s.chars()) {
+        Ok(n) => (Some(n), tag_of(n), n.char_len()),
+        // Too long with no bits reserved is too long with them too, but the
+        // room to report is what's left after reserving them.
+        Err(EncodeError::TooLong { page, needed, .. }) => (None, page.index(), needed),
+        Err(e) => return Err(e),
+    };
+    let max = if tag == CHINESE_4BIT_TAG {
+        nwidechars_reserving_low_bits::<N>(k)
+    } else {
+        nchars_reserving_low_bits::<N>(k)
+    };
+    match n {
+        Some(n) if needed <= max => Ok(n),
+        _ => Err(EncodeError::TooLong {
+            position: max,
+            page: Page::from_index(tag),
+            needed,
+            available: max,
+            fits_in: smallest_width(tag, needed, k),
+        }),
+    }
+}
+
+// Decode a word from `encode_reserving_low_bits`, ignoring its low `k` bits.
+pub fn decode_reserving_low_bits<N: PackedValue>(n: N, k: usize) -> DecodeSixbitIter<N> {
+    assert!(
+        k <= N::NCHARBITS + N::NPADBITS,
+        "sixbit::decode_reserving_low_bits"
+    );
+    let mut mask = !N::truncating_cast_from(0);
+    mask <<= k;
+    (n & mask).decode_sixbit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity() {
+        assert_eq!(nchars_reserving_low_bits::<u64>(0), 10);
+        assert_eq!(nchars_reserving_low_bits::<u64>(3), 9);
+        assert_eq!(nchars_reserving_low_bits::<u64>(6), 9);
+        assert_eq!(nchars_reserving_low_bits::<u64>(7), 8);
+        assert_eq!(nwidechars_reserving_low_bits::<u64>(3), 3);
+        assert_eq!(nchars_reserving_low_bits::<u32>(3), 4);
+        assert_eq!(nwidechars_reserving_low_bits::<u32>(1), 1);
+        assert_eq!(nchars_reserving_low_bits::<u128>(3), 20);
+        assert_eq!(nchars_reserving_low_bits::<u8>(3), 0);
+    }
+
+    #[test]
+    fn tagged() {
+        for w in ["", "x", "NO_CARRIE", "αλήθεια", "合伙人", "ｲｸﾂｶﾉ"].iter() {
+            let n: u64 = encode_reserving_low_bits(w, 3).unwrap();
+            assert_eq!(n & 0b111, 0);
+            for tag in 0..8 {
+                let s: String = decode_reserving_low_bits(n | tag, 3).collect();
+                assert_eq!(s, *w);
+            }
+        }
+        assert_eq!(
+            encode_reserving_low_bits::<u64>("NO_CARRIER", 3),
+            Err(EncodeError::TooLong {
+                position: 9,
+                page: Page::Latin,
+                needed: 10,
+                available: 9,
+                fits_in: Some(128),
+            })
+        );
+        assert_eq!(
+            encode_reserving_low_bits::<u64>("高速火车", 3),
+            Err(EncodeError::TooLong {
+                position: 3,
+                page: Page::Chinese,
+                needed: 4,
+                available: 3,
+                fits_in: Some(128),
+            })
+        );
+
+        // Too long for the value even without reserving anything.
+        assert_eq!(
+            encode_reserving_low_bits::<u64>("PRINTER_is_on_FIRE_ok", 3),
+            Err(EncodeError::TooLong {
+                position: 9,
+                page: Page::Latin,
+                needed: 21,
+                available: 9,
+                fits_in: Some(192),
+            })
+        );
+        assert_eq!(
+            encode_reserving_low_bits::<u64>("αλήθειαλήθει", 3),
+            Err(EncodeError::TooLong {
+                position: 9,
+                page: Page::Greek,
+                needed: 12,
+                available: 9,
+                fits_in: Some(192),
+            })
+        );
+        assert_eq!(
+            encode_reserving_low_bits::<u64>("高速火车站", 3),
+            Err(EncodeError::TooLong {
+                position: 3,
+                page: Page::Chinese,
+                needed: 5,
+                available: 3,
+                fits_in: Some(128),
+            })
+        );
+        assert_eq!(
+            encode_reserving_low_bits::<u128>("高速火车站再见一", 7),
+            Err(EncodeError::TooLong {
+                position: 7,
+                page: Page::Chinese,
+                needed: 8,
+                available: 7,
+                fits_in: Some(192),
+            })
+        );
+        assert_eq!(
+            encode_reserving_low_bits::<u32>("Hello", 1),
+            Err(EncodeError::TooLong {
+                position: 4,
+                page: Page::Latin,
+                needed: 5,
+                available: 4,
+                fits_in: Some(64),
+            })
+        );
+        assert_eq!(
+            encode_reserving_low_bits::<u64>("sh@rk", 3),
+            Err(EncodeError::MissingFromPage {
+                position: 2,
+                c: '@',
+                page: Page::Latin,
+            })
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let words = [
+            "",
+            "A",
+            "AB",
+            "a",
+            "ab",
+            "abcdefghi",
+            "b",
+            "αβγ",
+            "一",
+            "一丁",
+        ];
+        let tagged: Vec<u64> = words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| encode_reserving_low_bits::<u64>(w, 3).unwrap() | (7 - i as u64 % 8))
+            .collect();
+        for pair in tagged.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{encode, tag_of, DecodeSixbit, DecodeSixbitIter, PackedValue, CHINESE_4BIT_TAG};
+
+// Packed strings boxed in the payload of a quiet NaN, for dynamic-language
+// values that are either a double or something else.
+//
+// The top 16 bits are the sign, the all-ones exponent, the quiet bit and one
+// more payload bit set to mark a sixbit box: 0xfff9. The hardware's own NaNs
+// (0x7ff8... or 0xfff8...) never have it. The remaining 48 bits are the top
+// 48 bits of the string's u64 encoding, so they keep the 4-bit page tag, have
+// room for 7 6-bit codes or 2 15-bit codes, and compare in string order: the
+// boxes' `to_bits()` sort the same as the strings.
+//
+// Most platforms preserve NaN payloads through moves and `f64::from_bits`,
+// but arithmetic on a box may not; it should only ever be stored and loaded.
+
+const NANBOX_TAG: u64 = 0xfff9 << 48;
+const NANBOX_SHIFT: usize = 16;
+const NANBOX_CHARS: usize = (u64::NCHARBITS - NANBOX_SHIFT) / 6;
+const NANBOX_WIDECHARS: usize = (u64::NCHARBITS - NANBOX_SHIFT) / 15;
+
+pub fn is_nanbox(f: f64) -> bool {
+    f.to_bits() >> 48 == NANBOX_TAG >> 48
+}
+
+pub fn encode_nanbox(s: &str) -> Option<f64> {
+    let n: u64 = encode(s.chars()).ok()?;
+    let max = if tag_of(n) == CHINESE_4BIT_TAG {
+        NANBOX_WIDECHARS
+    } else {
+        NANBOX_CHARS
+    };
+    if n.char_len() > max {
+        return None;
+    }
+    Some(f64::from_bits(NANBOX_TAG | n >> NANBOX_SHIFT))
+}
+
+pub fn decode_nanbox(f: f64) -> Option<DecodeSixbitIter<u64>> {
+    if is_nanbox(f) {
+        Some((f.to_bits() << NANBOX_SHIFT).decode_sixbit())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(s: &str) -> Option<String> {
+        encode_nanbox(s)
+            .and_then(decode_nanbox)
+            .map(|it| it.collect())
+    }
+
+    #[test]
+    fn boxing() {
+        for w in [
+            "",
+            "x",
+            "NO_CARR",
+            "αλήθεια",
+            "ЩУКА",
+            "ㅇㅜㅁ",
+            "同事",
+            "ｲｸﾂｶﾉ",
+        ]
+        .iter()
+        {
+            let f = encode_nanbox(w).unwrap();
+            assert!(f.is_nan());
+            assert!(is_nanbox(f));
+            assert_eq!(round_trip(w).unwrap(), *w);
+        }
+        assert_eq!(encode_nanbox("NO_CARRI"), None);
+        assert_eq!(encode_nanbox("合伙人"), None);
+        assert_eq!(encode_nanbox("sh@rk"), None);
+
+        // Ordinary doubles, including the hardware NaNs, aren't boxes.
+        for &f in [0.0, -1.5, f64::INFINITY, f64::NAN, -f64::NAN].iter() {
+            assert!(!is_nanbox(f));
+            assert!(decode_nanbox(f).is_none());
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let words = [
+            "",
+            "A",
+            "AB",
+            "a",
+            "ab",
+            "abcdefg",
+            "b",
+            "αβγ",
+            "абв",
+            "一",
+            "一丁",
+            "ｦｧｨ",
+        ];
+        let bits: Vec<u64> = words
+            .iter()
+            .map(|w| encode_nanbox(w).unwrap().to_bits())
+            .collect();
+        for pair in bits.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use core::iter::{once, Once};
+
+use unicode_normalization::{Decompositions, Recompositions, UnicodeNormalization};
+
+use crate::{encode, DecodeSixbit, DecodeSixbitIter, EncodeError, PackedValue};
+
+// The pages only hold one form of each character, so text arriving in any
+// other form has to be mapped onto it before lookup:
+//
+//   - Greek is composed (NFC), which turns a letter followed by a combining
+//     acute into the precomposed tonos form the page holds, and also maps the
+//     polytonic oxia forms (U+1F71 etc.) onto their tonos equivalents.
+//
+//   - Devanagari is decomposed (NFD), since the page has the nukta but not
+//     the nukta consonants U+0958-U+095F, which are split into consonant and
+//     nukta.
+//
+//   - Arabic is decomposed as well (NFD), splitting letters with hamza or
+//     maddah above into the base letter and the combining mark, and the
+//     presentation forms in U+FB50-U+FDFF and U+FE70-U+FEFF are mapped to
+//     their base letters (NFKD).
+//
+// Other characters are left alone, so anything that didn't encode before
+// still fails on the character the caller passed in.
+
+fn is_greek(c: char) -> bool {
+    ('\u{0370}'..='\u{03ff}').contains(&c) || ('\u{1f00}'..='\u{1fff}').contains(&c)
+}
+
+fn is_arabic_presentation_form(c: char) -> bool {
+    ('\u{fb50}'..='\u{fdff}').contains(&c) || ('\u{fe70}'..='\u{feff}').contains(&c)
+}
+
+fn is_decomposed(c: char) -> bool {
+    ('\u{0600}'..='\u{06ff}').contains(&c) || ('\u{0900}'..='\u{097f}').contains(&c)
+}
+
+enum Mapped {
+    Kept(Once<char>),
+    Decomposed(Decompositions<Once<char>>),
+}
+
+impl Iterator for Mapped {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        match self {
+            Mapped::Kept(it) => it.next(),
+            Mapped::Decomposed(it) => it.next(),
+        }
+    }
+}
+
+fn map_char(c: char) -> Mapped {
+    if is_arabic_presentation_form(c) {
+        Mapped::Decomposed(once(c).nfkd())
+    } else if is_decomposed(c) {
+        Mapped::Decomposed(once(c).nfd())
+    } else {
+        Mapped::Kept(once(c))
+    }
+}
+
+pub fn encode_normalized<N>(s: &str) -> Result<N, EncodeError>
+where
+    N: PackedValue,
+{
+    match s.chars().next() {
+        Some(c) if is_greek(c) => encode(s.nfc()),
+        _ => encode(s.chars().flat_map(map_char)),
+    }
+}
+
+// Decode and recompose to NFC, which puts back the Arabic letters with hamza
+// or maddah. The Devanagari nukta consonants are excluded from composition
+// by Unicode, so they stay decomposed: that's the normal form other NFC text
+// will have them in too.
+pub fn decode_normalized<N>(n: N) -> Recompositions<DecodeSixbitIter<N>>
+where
+    N: PackedValue,
+{
+    n.decode_sixbit().nfc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Page;
+
+    fn round_trip(s: &str) -> String {
+        decode_normalized(encode_normalized::<u64>(s).unwrap()).collect()
+    }
+
+    #[test]
+    fn greek() {
+        // Tonos, oxia, and decomposed acute all encode the same.
+        let tonos = encode::<u64, _>("άέή".chars());
+        assert_eq!(encode_normalized("\u{3ac}\u{3ad}\u{3ae}"), tonos);
+        assert_eq!(encode_normalized("\u{1f71}\u{1f73}\u{1f75}"), tonos);
+        assert_eq!(
+            encode_normalized("\u{3b1}\u{301}\u{3b5}\u{301}\u{3b7}\u{301}"),
+            tonos
+        );
+        assert_eq!(
+            round_trip("αλ\u{1f75}θεια"),
+            "αλήθεια".nfc().collect::<String>()
+        );
+    }
+
+    #[test]
+    fn devanagari() {
+        // U+095B ZA is JA followed by nukta.
+        let za = "\u{95b}ि\u{95b}";
+        assert!(encode::<u64, _>(za.chars()).is_err());
+        assert_eq!(
+            encode_normalized::<u64>(za),
+            encode("\u{91c}\u{93c}ि\u{91c}\u{93c}".chars())
+        );
+        assert_eq!(round_trip(za), "\u{91c}\u{93c}ि\u{91c}\u{93c}");
+    }
+
+    #[test]
+    fn arabic() {
+        // Isolated, initial, medial and final presentation forms of beh,
+        // then alef with maddah above.
+        let forms = "\u{fe8f}\u{fe91}\u{fe92}\u{fe90}\u{622}";
+        assert_eq!(
+            encode_normalized::<u64>(forms),
+            encode("\u{628}\u{628}\u{628}\u{628}\u{627}\u{653}".chars())
+        );
+        assert_eq!(round_trip(forms), "\u{628}\u{628}\u{628}\u{628}\u{622}");
+    }
+
+    #[test]
+    fn unchanged() {
+        assert_eq!(
+            encode_normalized::<u64>("NO_CARRIER"),
+            encode("NO_CARRIER".chars())
+        );
+        assert_eq!(encode_normalized::<u64>("合伙人"), encode("合伙人".chars()));
+        // Only the pages above are mapped: Latin accents still fail as themselves.
+        assert_eq!(
+            encode_normalized::<u64>("café"),
+            Err(EncodeError::MissingFromPage {
+                position: 3,
+                c: 'é',
+                page: Page::Latin,
+            })
+        );
+        assert_eq!(round_trip(""), "");
+    }
+}
@@ -0,0 +1,148 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+use crate::{PackedValue, Sixbit};
+
+// Human-readable formats (JSON, YAML, ...) get the decoded string, since
+// that's what anyone reading the output wants to see; binary formats get the
+// raw integer, since that's smaller and faster. Either way deserializing checks
+// the result, by encoding the string or validating the integer.
+
+impl<N> Serialize for Sixbit<N>
+where
+    N: PackedValue + Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.get().serialize(serializer)
+        }
+    }
+}
+
+struct SixbitVisitor<N>(PhantomData<N>);
+
+impl<'de, N> Visitor<'de> for SixbitVisitor<N>
+where
+    N: PackedValue,
+{
+    type Value = Sixbit<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a string that encodes in {} bits", N::NBITS)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Sixbit<N>, E> {
+        Sixbit::encode(v.chars()).map_err(E::custom)
+    }
+}
+
+impl<'de, N> Deserialize<'de> for Sixbit<N>
+where
+    N: PackedValue + Deserialize<'de>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(SixbitVisitor(PhantomData))
+        } else {
+            let n = N::deserialize(deserializer)?;
+            Sixbit::new(n).map_err(de::Error::custom)
+        }
+    }
+}
+
+// For raw packed values in serialized structs, which can't have their own
+// impls: `#[serde(with = "sixbit::serde_packed")] key: u64` treats the field
+// the same way as a `Sixbit64`, validating it on the way out as well as in.
+pub mod serde_packed {
+    use super::*;
+
+    pub fn serialize<N, S>(n: &N, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        N: PackedValue + Serialize,
+        S: Serializer,
+    {
+        // Refuse what `deserialize` would, rather than write out something
+        // that can't be read back.
+        Sixbit::new(*n)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, N, D>(deserializer: D) -> Result<N, D::Error>
+    where
+        N: PackedValue + Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Sixbit::<N>::deserialize(deserializer).map(Sixbit::get)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::{EncodeSixbit, Sixbit128, Sixbit64};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Record {
+        name: Sixbit64,
+        title: Sixbit128,
+        #[serde(with = "crate::serde_packed")]
+        raw: u64,
+    }
+
+    fn record() -> Record {
+        Record {
+            name: "NO_CARRIER".parse().unwrap(),
+            title: "合伙人".parse().unwrap(),
+            raw: "abc".chars().encode_sixbit().unwrap(),
+        }
+    }
+
+    #[test]
+    fn json() {
+        let json = serde_json::to_string(&record()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"NO_CARRIER","title":"合伙人","raw":"abc"}"#
+        );
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record());
+
+        let bad = r#"{"name":"sh@rk","title":"","raw":""}"#;
+        let err = serde_json::from_str::<Record>(bad).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'@' at position 2 isn't in the Latin page"));
+        let bad = r#"{"name":"","title":"","raw":"ATDT_123_4567"}"#;
+        let err = serde_json::from_str::<Record>(bad).unwrap_err();
+        assert!(err.to_string().contains("13 Latin characters don't fit"));
+    }
+
+    #[test]
+    fn bincode() {
+        let bytes = bincode::serialize(&record()).unwrap();
+        assert_eq!(bytes.len(), 8 + 16 + 8);
+        assert_eq!(bincode::deserialize::<Record>(&bytes).unwrap(), record());
+
+        let bad = bincode::serialize(&(0x5u64 << 60)).unwrap();
+        let err = bincode::deserialize::<Sixbit64>(&bad).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("tag 5 selects a page that's reserved at this width"));
+
+        #[derive(Serialize)]
+        struct Raw {
+            #[serde(with = "crate::serde_packed")]
+            raw: u64,
+        }
+        let err = bincode::serialize(&Raw { raw: 0x5u64 << 60 }).unwrap_err();
+        assert!(err.to_string().contains("tag 5 selects a page"));
+        let err = serde_json::to_string(&Raw { raw: 1 }).unwrap_err();
+        assert!(err.to_string().contains("nonzero bits after the end"));
+    }
+}
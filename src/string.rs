@@ -0,0 +1,271 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+use crate::{encode, DecodeSixbit, SixbitStr};
+
+// A string that is stored inline as a packed u128 when it encodes, and on the
+// heap as a Box<str> when it doesn't, in 16 bytes either way.
+//
+// Every nonempty packed u128 has a nonzero top byte: it's the 2-bit tag and
+// the first 6 bits of a code, and codes are never zero. So a nonzero value
+// with a zero top byte can't be a packed string, and that's what marks the
+// heap case, with the length in the high word and the pointer in the low one.
+// The empty string is the packed value 0.
+//
+// Strings are always stored inline if they encode, so two strings are equal
+// only if they're stored the same way; ordering across the two needs the
+// inline string decoded, which is done into a buffer on the stack.
+pub struct SixbitString {
+    hi: u64,
+    lo: Lo,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+union Lo {
+    bits: u64,
+    ptr: *mut u8,
+}
+
+// The SixbitString owns its Box<str>, so it's as thread-safe as one.
+unsafe impl Send for SixbitString {}
+unsafe impl Sync for SixbitString {}
+
+impl SixbitString {
+    pub fn new(s: &str) -> SixbitString {
+        match encode::<u128, _>(s.chars()) {
+            Ok(n) => SixbitString::inline(n),
+            Err(_) => SixbitString::heap(s.into()),
+        }
+    }
+
+    fn inline(n: u128) -> SixbitString {
+        SixbitString {
+            hi: (n >> 64) as u64,
+            lo: Lo { bits: n as u64 },
+        }
+    }
+
+    fn heap(b: Box<str>) -> SixbitString {
+        let len = b.len() as u64;
+        // Empty strings always encode, and nothing is 2^56 bytes long.
+        assert!(len != 0 && len >> 56 == 0);
+        SixbitString {
+            hi: len,
+            lo: Lo {
+                ptr: Box::into_raw(b) as *mut u8,
+            },
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.hi == 0 || self.hi >> 56 != 0
+    }
+
+    // The packed value, if the string is stored inline.
+    pub fn packed(&self) -> Option<u128> {
+        if self.is_inline() {
+            // Safe because inline strings always set `bits`.
+            let lo = unsafe { self.lo.bits };
+            Some((self.hi as u128) << 64 | lo as u128)
+        } else {
+            None
+        }
+    }
+
+    fn heap_str(&self) -> &str {
+        // Safe because heap strings always set `ptr` to a Box<str> of `hi`
+        // bytes, which lives as long as self does.
+        unsafe {
+            let bytes = core::slice::from_raw_parts(self.lo.ptr, self.hi as usize);
+            core::str::from_utf8_unchecked(bytes)
+        }
+    }
+
+    // Call `f` with the string, decoding it into a stack buffer if it's inline.
+    // This never allocates, unlike `as_str`.
+    pub fn with_str<R>(&self, f: impl FnOnce(&str) -> R) -> R {
+        match self.packed() {
+            None => f(self.heap_str()),
+            Some(n) => f(&SixbitStr::from(n)),
+        }
+    }
+
+    // The string, borrowed if it's on the heap; an inline string has nowhere
+    // to be borrowed from, so it's decoded into a new String. Use `with_str`
+    // to read it without allocating.
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match self.packed() {
+            None => Cow::Borrowed(self.heap_str()),
+            Some(n) => Cow::Owned(n.decode_sixbit().collect()),
+        }
+    }
+}
+
+impl Drop for SixbitString {
+    fn drop(&mut self) {
+        if !self.is_inline() {
+            // Safe because this is the Box<str> that `heap` leaked.
+            unsafe {
+                let s = core::ptr::slice_from_raw_parts_mut(self.lo.ptr, self.hi as usize);
+                drop(Box::from_raw(s as *mut str));
+            }
+        }
+    }
+}
+
+impl Clone for SixbitString {
+    fn clone(&self) -> SixbitString {
+        match self.packed() {
+            Some(n) => SixbitString::inline(n),
+            None => SixbitString::heap(self.heap_str().into()),
+        }
+    }
+}
+
+impl Default for SixbitString {
+    fn default() -> SixbitString {
+        SixbitString::inline(0)
+    }
+}
+
+impl<'a> From<&'a str> for SixbitString {
+    fn from(s: &'a str) -> SixbitString {
+        SixbitString::new(s)
+    }
+}
+
+impl From<String> for SixbitString {
+    fn from(s: String) -> SixbitString {
+        match encode::<u128, _>(s.chars()) {
+            Ok(n) => SixbitString::inline(n),
+            Err(_) => SixbitString::heap(s.into_boxed_str()),
+        }
+    }
+}
+
+impl PartialEq for SixbitString {
+    fn eq(&self, other: &SixbitString) -> bool {
+        match (self.packed(), other.packed()) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => self.heap_str() == other.heap_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for SixbitString {}
+
+impl PartialOrd for SixbitString {
+    fn partial_cmp(&self, other: &SixbitString) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SixbitString {
+    fn cmp(&self, other: &SixbitString) -> Ordering {
+        match (self.packed(), other.packed()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => self.with_str(|a| other.with_str(|b| a.cmp(b))),
+        }
+    }
+}
+
+impl Hash for SixbitString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash as the str, so it doesn't matter where it's stored.
+        self.with_str(|s| s.hash(state))
+    }
+}
+
+impl fmt::Display for SixbitString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with_str(|s| fmt::Display::fmt(s, f))
+    }
+}
+
+impl fmt::Debug for SixbitString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with_str(|s| fmt::Debug::fmt(s, f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    const WORDS: [&str; 16] = [
+        "",
+        "NO_CARRIER",
+        "PRINTER_FULLY_OPERATIONAL",
+        "PRINTER_is_on_FIRE",
+        "a",
+        "a b",
+        "ab",
+        "sh@rk",
+        "zzzzzzzzzzzzzzzzzzzzzz",
+        "αλήθεια",
+        "محافظت",
+        "किंकर्तव्यविमूढ़",
+        "同事",
+        "高速火车站",
+        "高速火车站高速火车站",
+        "ｲｸﾂｶﾉ",
+    ];
+
+    #[test]
+    fn storage() {
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(core::mem::size_of::<SixbitString>(), 16);
+
+        for w in WORDS.iter() {
+            let s = SixbitString::from(*w);
+            assert_eq!(s.as_str(), *w);
+            assert!(s.with_str(|t| t == *w));
+            assert_eq!(matches!(s.as_str(), Cow::Owned(_)), s.is_inline());
+            assert_eq!(s.to_string(), *w);
+            assert_eq!(format!("{:?}", s), format!("{:?}", w));
+            assert_eq!(s.is_inline(), encode::<u128, _>(w.chars()).is_ok());
+            assert_eq!(s.packed(), encode(w.chars()).ok());
+            assert_eq!(s.clone(), s);
+            assert_eq!(SixbitString::from(w.to_string()), s);
+        }
+        assert!(SixbitString::from("").is_inline());
+        assert!(SixbitString::default() == SixbitString::from(""));
+        assert!(!SixbitString::from("sh@rk").is_inline());
+    }
+
+    #[test]
+    fn ordering() {
+        let mut strings: Vec<SixbitString> = WORDS.iter().rev().map(|&w| w.into()).collect();
+        strings.sort();
+        let sorted: Vec<String> = strings.iter().map(|s| s.to_string()).collect();
+        assert_eq!(sorted, WORDS);
+
+        for a in WORDS.iter() {
+            for b in WORDS.iter() {
+                let (x, y) = (SixbitString::from(*a), SixbitString::from(*b));
+                assert_eq!(x.cmp(&y), a.cmp(b), "{:?} {:?}", a, b);
+                assert_eq!(x == y, a == b);
+            }
+        }
+    }
+
+    #[test]
+    fn hashing() {
+        let hash = |x: &dyn Fn(&mut DefaultHasher)| {
+            let mut h = DefaultHasher::new();
+            x(&mut h);
+            h.finish()
+        };
+        for w in WORDS.iter() {
+            let s = SixbitString::from(*w);
+            assert_eq!(hash(&|h| s.hash(h)), hash(&|h| w.hash(h)));
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::string::String;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+use std::vec::Vec;
+
+use crate::{encode, DecodeSixbit};
+
+// Interned names. Most identifiers encode in a u64, and those are their own
+// symbol: interning them is just encoding, and resolving them is decoding,
+// so they never touch the table. Anything else goes in a side table, and its
+// symbol is the index, tagged with page 0101, which is reserved in a u64 so
+// the encoder never produces it. The 28 bits between the tag and the 32-bit
+// index hold an id for the table, so that a table can tell its own symbols
+// from another's; ids only repeat after 2^28 tables.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Symbol(u64);
+
+const SIDE_TABLE_TAG: u64 = 0b0101 << 60;
+const TAG_MASK: u64 = 0b1111 << 60;
+const TABLE_ID_BITS: u32 = 28;
+const INDEX_BITS: u32 = 32;
+
+static NEXT_TABLE_ID: AtomicU32 = AtomicU32::new(0);
+
+fn next_table_id() -> u32 {
+    NEXT_TABLE_ID.fetch_add(1, Ordering::Relaxed) & ((1 << TABLE_ID_BITS) - 1)
+}
+
+impl Symbol {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_inline(self) -> bool {
+        self.0 & TAG_MASK != SIDE_TABLE_TAG
+    }
+
+    // The index of a side-table symbol, if it's from the table with this id.
+    fn index(self, table_id: u32) -> Option<usize> {
+        debug_assert!(!self.is_inline());
+        if (self.0 & !TAG_MASK) >> INDEX_BITS != table_id as u64 {
+            None
+        } else {
+            Some((self.0 & ((1 << INDEX_BITS) - 1)) as usize)
+        }
+    }
+
+    fn from_index(table_id: u32, i: usize) -> Symbol {
+        assert!((i as u64) >> INDEX_BITS == 0, "sixbit::SymbolTable is full");
+        Symbol(SIDE_TABLE_TAG | (table_id as u64) << INDEX_BITS | i as u64)
+    }
+}
+
+pub struct SymbolTable {
+    id: u32,
+    names: Vec<Arc<str>>,
+    index: HashMap<Arc<str>, Symbol>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> SymbolTable {
+        SymbolTable {
+            id: next_table_id(),
+            names: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Ok(n) = encode(name.chars()) {
+            return Symbol(n);
+        }
+        if let Some(&sym) = self.index.get(name) {
+            return sym;
+        }
+        let sym = Symbol::from_index(self.id, self.names.len());
+        let name: Arc<str> = name.into();
+        self.names.push(name.clone());
+        self.index.insert(name, sym);
+        sym
+    }
+
+    // The name of a symbol, or None if it's from some other table.
+    pub fn resolve(&self, sym: Symbol) -> Option<Cow<'_, str>> {
+        if sym.is_inline() {
+            return Some(Cow::Owned(sym.0.decode_sixbit().collect()));
+        }
+        let i = sym.index(self.id)?;
+        self.names.get(i).map(|s| Cow::Borrowed(&**s))
+    }
+
+    // The number of names in the side table; inline symbols aren't counted.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+// The same, behind a lock. Inline symbols are still made and resolved without
+// touching it; names resolved from the side table are copied out, since they
+// can't be borrowed past the lock.
+#[derive(Default)]
+pub struct SyncSymbolTable {
+    table: RwLock<SymbolTable>,
+}
+
+impl SyncSymbolTable {
+    pub fn new() -> SyncSymbolTable {
+        SyncSymbolTable::default()
+    }
+
+    pub fn intern(&self, name: &str) -> Symbol {
+        if let Ok(n) = encode(name.chars()) {
+            return Symbol(n);
+        }
+        if let Some(&sym) = self.table.read().unwrap().index.get(name) {
+            return sym;
+        }
+        // Someone else may have got here first, which `intern` checks for.
+        self.table.write().unwrap().intern(name)
+    }
+
+    pub fn resolve(&self, sym: Symbol) -> Option<String> {
+        if sym.is_inline() {
+            return Some(sym.0.decode_sixbit().collect());
+        }
+        let table = self.table.read().unwrap();
+        table.resolve(sym).map(|s| s.into_owned())
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.read().unwrap().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 8] = [
+        "x",
+        "NO_CARRIER",
+        "αλήθεια",
+        "合伙人",
+        "snake_case_name",
+        "with-dash",
+        "CamelCaseIdentifier",
+        "",
+    ];
+
+    #[test]
+    fn interning() {
+        let mut t = SymbolTable::new();
+        let syms: Vec<Symbol> = NAMES.iter().map(|n| t.intern(n)).collect();
+        assert_eq!(t.len(), 3);
+        for (n, &s) in NAMES.iter().zip(syms.iter()) {
+            assert_eq!(s.is_inline(), encode::<u64, _>(n.chars()).is_ok());
+            assert_eq!(t.intern(n), s);
+            assert_eq!(t.resolve(s).unwrap(), *n);
+        }
+        assert_eq!(t.len(), 3);
+        assert_eq!(Ok(t.intern("x").get()), encode("x".chars()));
+
+        // Side-table symbols from another table don't resolve.
+        let mut u = SymbolTable::new();
+        for _ in 0..3 {
+            u.intern("one-two");
+        }
+        assert_eq!(u.len(), 1);
+        assert_eq!(u.resolve(syms[6]), None);
+        assert_eq!(u.resolve(syms[0]).unwrap(), "x");
+        // Even one whose index is in range, like the first, "snake_case_name".
+        assert_eq!(u.resolve(syms[4]), None);
+        assert_eq!(t.resolve(u.intern("one-two")), None);
+    }
+
+    #[test]
+    fn threads() {
+        let t = Arc::new(SyncSymbolTable::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let t = t.clone();
+                std::thread::spawn(move || NAMES.iter().map(|n| t.intern(n)).collect::<Vec<_>>())
+            })
+            .collect();
+        let results: Vec<Vec<Symbol>> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        for r in results.iter() {
+            assert_eq!(*r, results[0]);
+        }
+        assert_eq!(t.len(), 3);
+        for (n, &s) in NAMES.iter().zip(results[0].iter()) {
+            assert_eq!(t.resolve(s).unwrap(), *n);
+        }
+        let u = SyncSymbolTable::new();
+        assert_eq!(u.resolve(u.intern("with-dash")).unwrap(), "with-dash");
+        assert_eq!(u.resolve(results[0][4]), None);
+        assert_eq!(t.resolve(u.intern("with-dash")), None);
+    }
+}
@@ -0,0 +1,183 @@
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{
+    encode, validate, DecodeError, DecodeSixbit, DecodeSixbitIter, EncodeError, PackedValue,
+    SixbitStr, Wide, U192, U256,
+};
+
+// A packed value that is known to hold a validly-encoded string. The only
+// ways to get one are to encode a string or to validate an existing value, so
+// code holding a `Sixbit64` never has to wonder whether its `u64` is a string.
+// Comparison is the same integer comparison as on the raw value, which the
+// encoding guarantees matches string order.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Sixbit<N>(pub(crate) N);
+
+pub type Sixbit8 = Sixbit<u8>;
+pub type Sixbit16 = Sixbit<u16>;
+pub type Sixbit32 = Sixbit<u32>;
+pub type Sixbit64 = Sixbit<u64>;
+pub type Sixbit128 = Sixbit<u128>;
+pub type Sixbit192 = Sixbit<U192>;
+pub type Sixbit256 = Sixbit<U256>;
+
+impl<N> Sixbit<N>
+where
+    N: PackedValue,
+{
+    pub fn encode<IT>(i: IT) -> Result<Self, EncodeError>
+    where
+        IT: Iterator<Item = char>,
+    {
+        encode::<N, IT>(i).map(Sixbit)
+    }
+
+    pub fn new(n: N) -> Result<Self, DecodeError> {
+        validate(n)?;
+        Ok(Sixbit(n))
+    }
+
+    pub fn get(self) -> N {
+        self.0
+    }
+
+    pub fn chars(self) -> DecodeSixbitIter<N> {
+        self.0.decode_sixbit()
+    }
+}
+
+// Generates only valid strings, so fuzzers deriving `Arbitrary` for structs
+// holding these explore the encodable space rather than garbage integers.
+#[cfg(feature = "arbitrary")]
+impl<'a, N> arbitrary::Arbitrary<'a> for Sixbit<N>
+where
+    N: PackedValue,
+{
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        <N as PackedValue>::arbitrary(u).map(Sixbit)
+    }
+}
+
+impl<N> AsRef<N> for Sixbit<N> {
+    fn as_ref(&self) -> &N {
+        &self.0
+    }
+}
+
+impl<N> fmt::Display for Sixbit<N>
+where
+    N: PackedValue,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        if let Some(s) = SixbitStr::new(self.get()) {
+            return f.write_str(&s);
+        }
+        for c in self.chars() {
+            f.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+impl<N> fmt::Debug for Sixbit<N>
+where
+    N: PackedValue,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        write!(f, "Sixbit{}(\"", N::NBITS)?;
+        for c in self.chars() {
+            for e in c.escape_debug() {
+                f.write_char(e)?;
+            }
+        }
+        f.write_str("\")")
+    }
+}
+
+impl<N> FromStr for Sixbit<N>
+where
+    N: PackedValue,
+{
+    type Err = EncodeError;
+    fn from_str(s: &str) -> Result<Self, EncodeError> {
+        Self::encode(s.chars())
+    }
+}
+
+impl<'a, N> TryFrom<&'a str> for Sixbit<N>
+where
+    N: PackedValue,
+{
+    type Error = EncodeError;
+    fn try_from(s: &'a str) -> Result<Self, EncodeError> {
+        Self::encode(s.chars())
+    }
+}
+
+macro_rules! impl_from_sixbit {
+    ($($t:ty),*) => {
+        $(
+            impl From<Sixbit<$t>> for $t {
+                fn from(s: Sixbit<$t>) -> $t {
+                    s.0
+                }
+            }
+        )*
+    };
+}
+
+impl_from_sixbit!(u8, u16, u32, u64, u128);
+
+impl<const L: usize> From<Sixbit<Wide<L>>> for Wide<L> {
+    fn from(s: Sixbit<Wide<L>>) -> Wide<L> {
+        s.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn construction() {
+        let s: Sixbit64 = "NO_CARRIER".parse().unwrap();
+        assert_eq!(s.to_string(), "NO_CARRIER");
+        assert_eq!(format!("{:?}", s), "Sixbit64(\"NO_CARRIER\")");
+        assert_eq!(Sixbit64::new(s.get()), Ok(s));
+        assert_eq!(u64::from(s), *s.as_ref());
+
+        assert_eq!(
+            Sixbit32::try_from("aaaaaaa").unwrap_err().to_string(),
+            "7 Latin characters don't fit in room for 5, but would in 64 bits"
+        );
+        assert_eq!(Sixbit64::new(0x5 << 60), Err(DecodeError::ReservedTag(5)));
+        assert_eq!(Sixbit8::default().to_string(), "");
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary() {
+        use arbitrary::{Arbitrary, Unstructured};
+        let bytes: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut u = Unstructured::new(&bytes);
+        while !u.is_empty() {
+            let s = Sixbit64::arbitrary(&mut u).unwrap();
+            assert_eq!(Sixbit64::new(s.get()), Ok(s));
+            assert_eq!(s.to_string().parse(), Ok(s));
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let words = ["", "AB", "abcd", "abcde", "αβγ", "абв", "合伙人", "ｦｧｨ"];
+        let packed: Vec<Sixbit64> = words.iter().map(|w| w.parse().unwrap()).collect();
+        for pair in packed.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].get() < pair[1].get());
+        }
+    }
+}
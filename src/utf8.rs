@@ -0,0 +1,684 @@
+use core::cell::Cell;
+use core::cmp::{min, Ordering};
+use core::convert::TryInto;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+use crate::{
+    code_bits, encode, DecodeSixbit, EncodeError, PackedValue, CHINESE_4BIT_TAG, CHINESE_LO, PAGES,
+};
+
+// Encoding straight from UTF-8 bytes. Most strings worth packing are ASCII
+// identifiers on the Latin page, and those are done 8 bytes at a time in a
+// u64: each byte is classified as a digit, uppercase, underscore or lowercase
+// with a few subtractions, turned into its code by subtracting its class's
+// offset, and the 8 codes are squeezed together into 48 bits, all without
+// branching per byte.
+//
+// Anything else, including an ASCII string with a byte that isn't on the
+// page, goes the general way: the bytes are decoded to chars for `encode`,
+// checking the UTF-8 in the same pass. So the result, and any error, is
+// always what `encode` gives.
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+const HIGH: u64 = 0x8080_8080_8080_8080;
+
+// What to subtract from each byte of a class to get its code: '0' is code 1,
+// 'A' 11, '_' 37 and 'a' 38.
+const DIGIT_OFFSET: u64 = ONES * (b'0' - 1) as u64;
+const UPPER_OFFSET: u64 = ONES * (b'A' - 11) as u64;
+const UNDER_OFFSET: u64 = ONES * (b'_' - 37) as u64;
+const LOWER_OFFSET: u64 = ONES * (b'a' - 38) as u64;
+
+// The high bit of every byte that is at least `k`, for bytes below 0x80: with
+// the high bit set first, subtracting `k` never borrows from the next byte.
+fn at_least(v: u64, k: u8) -> u64 {
+    ((v | HIGH) - ONES * k as u64) & HIGH
+}
+
+// The Latin codes of 8 ASCII bytes, first byte highest, packed into the low
+// 48 bits. `real` has the high bit of each byte that is part of the string;
+// the rest must be zero, and give zero codes. None if any real byte isn't on
+// the page.
+fn latin_codes(v: u64, real: u64) -> Option<u64> {
+    if v & HIGH != 0 {
+        return None;
+    }
+    let digit = at_least(v, b'0') & !at_least(v, b'9' + 1);
+    let upper = at_least(v, b'A') & !at_least(v, b'Z' + 1);
+    let under = at_least(v, b'_') & !at_least(v, b'_' + 1);
+    let lower = at_least(v, b'a') & !at_least(v, b'z' + 1);
+    if digit | upper | under | lower != real {
+        return None;
+    }
+    // Spread each class's high bits over their bytes to select its offset.
+    let spread = |m: u64| (m >> 7) * 0xff;
+    let offset = (spread(digit) & DIGIT_OFFSET)
+        | (spread(upper) & UPPER_OFFSET)
+        | (spread(under) & UNDER_OFFSET)
+        | (spread(lower) & LOWER_OFFSET);
+    let c = v - offset;
+    // Pairs of codes into 12 bits, then 24, then 48.
+    let c = (c & 0x003f_003f_003f_003f) | (c & 0x3f00_3f00_3f00_3f00) >> 2;
+    let c = (c & 0x0000_0fff_0000_0fff) | (c & 0x0fff_0000_0fff_0000) >> 4;
+    Some((c & 0x0000_0000_00ff_ffff) | (c & 0x00ff_ffff_0000_0000) >> 8)
+}
+
+// Up to 8 bytes, first byte highest, zero-padded.
+fn load(chunk: &[u8]) -> u64 {
+    match chunk.try_into() {
+        Ok(full) => u64::from_be_bytes(full),
+        Err(_) => chunk
+            .iter()
+            .enumerate()
+            .fold(0, |v, (i, &b)| v | (b as u64) << (56 - 8 * i)),
+    }
+}
+
+fn encode_latin<N: PackedValue>(b: &[u8]) -> Option<Result<N, EncodeError>> {
+    if b.len() > N::NCHARS {
+        // Too long, which is all `encode` would say if every byte is Latin.
+        for chunk in b.chunks(8) {
+            latin_codes(load(chunk), HIGH << (8 * (8 - chunk.len())))?;
+        }
+        return Some(Err(EncodeError::too_long(N::NCHARS, 0, b.len(), N::NCHARS)));
+    }
+    let mut out = N::truncating_cast_from(0);
+    for chunk in b.chunks(8) {
+        let real = HIGH << (8 * (8 - chunk.len()));
+        let bits = 6 * chunk.len();
+        let codes = latin_codes(load(chunk), real)? >> (48 - bits);
+        // In two halves, so each fits in a 32-bit usize.
+        if bits > 24 {
+            out <<= bits - 24;
+            out |= N::truncating_cast_from((codes >> 24) as usize);
+            out <<= 24;
+            out |= N::truncating_cast_from((codes & 0xff_ffff) as usize);
+        } else {
+            out <<= bits;
+            out |= N::truncating_cast_from(codes as usize);
+        }
+    }
+    // Pad remainder; the Latin tag is zero.
+    out <<= 6 * (N::NCHARS - b.len()) + N::NPADBITS;
+    Some(Ok(out))
+}
+
+// The chars of some UTF-8 bytes, checking them as it goes: it stops at the
+// first bad sequence, setting `bad` to the number of chars before it. Its
+// methods are inlined, since it isn't generic and `encode` calls them for
+// every char.
+struct Utf8Chars<'a> {
+    b: core::slice::Iter<'a, u8>,
+    pos: usize,
+    bad: &'a Cell<Option<usize>>,
+}
+
+impl<'a> Utf8Chars<'a> {
+    // The low 6 bits of a continuation byte. Anything else is consumed too,
+    // which doesn't matter since nothing is read after a bad sequence.
+    #[inline]
+    fn cont(&mut self) -> Option<u32> {
+        match self.b.next() {
+            Some(&c) if c & 0xc0 == 0x80 => Some((c & 0x3f) as u32),
+            _ => None,
+        }
+    }
+
+    // The rest of a multi-byte char starting with `x`.
+    #[inline]
+    fn rest(&mut self, x: u8) -> Option<char> {
+        let x = x as u32;
+        let y = self.cont()?;
+        // Each form's smallest value, to refuse overlong ones; `from_u32`
+        // refuses surrogates and anything past U+10FFFF.
+        let (c, min) = if x < 0xe0 {
+            ((x & 0x1f) << 6 | y, 0x80)
+        } else if x < 0xf0 {
+            ((x & 0x0f) << 12 | y << 6 | self.cont()?, 0x800)
+        } else {
+            let c = (x & 0x07) << 18 | y << 12 | self.cont()? << 6;
+            (c | self.cont()?, 0x1_0000)
+        };
+        if (0xc0..0xf8).contains(&x) && c >= min {
+            char::from_u32(c)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Iterator for Utf8Chars<'a> {
+    type Item = char;
+
+    #[inline]
+    fn next(&mut self) -> Option<char> {
+        let x = *self.b.next()?;
+        let c = if x < 0x80 {
+            x as char
+        } else {
+            match self.rest(x) {
+                Some(c) => c,
+                None => {
+                    self.bad.set(Some(self.pos));
+                    return None;
+                }
+            }
+        };
+        self.pos += 1;
+        Some(c)
+    }
+}
+
+pub fn encode_utf8<N: PackedValue>(b: &[u8]) -> Result<N, EncodeError> {
+    // A string that starts with a non-ASCII byte can't be ASCII Latin.
+    if !matches!(b.first(), Some(&x) if x >= 0x80) {
+        if let Some(r) = encode_latin(b) {
+            return r;
+        }
+    }
+    let bad = Cell::new(None);
+    let chars = || Utf8Chars {
+        b: b.iter(),
+        pos: 0,
+        bad: &bad,
+    };
+    let r = encode(chars());
+    if r.is_err() && bad.get().is_none() && !b.is_ascii() {
+        // `encode` may have given up before the end, but bad UTF-8 anywhere
+        // takes precedence over whatever it found.
+        chars().for_each(drop);
+    }
+    match bad.get() {
+        Some(position) => Err(EncodeError::InvalidUtf8 { position }),
+        None => r,
+    }
+}
+
+// Decoding straight to UTF-8 bytes. Each page's characters are looked up in a
+// table of their UTF-8 bytes, built at compile time from PAGES, instead of
+// being decoded to a char and encoded again; Chinese codes are a fixed
+// distance from their characters, and all take 3 bytes.
+//
+// Latin values at least 64 bits wide go 16 codes at a time on x86_64 with
+// SSSE3: a byte shuffle puts the 3 bytes holding each group of 4 codes in a
+// 32-bit lane, two multiplies move each code into a byte of its own (the
+// trick base64 encoders use), and a few comparisons pick the offset that
+// turns each code into its ASCII byte.
+//
+// Both write whole table entries or vectors when `out` has room past the end
+// of the string, so that short strings don't cost a call to memcpy.
+
+// A character's UTF-8 bytes, first byte lowest, with their count in the top
+// byte.
+const fn utf8_entry(c: u32) -> u32 {
+    if c < 0x80 {
+        1 << 24 | c
+    } else if c < 0x800 {
+        2 << 24 | (0x80 | (c & 0x3f)) << 8 | (0xc0 | c >> 6)
+    } else {
+        3 << 24 | (0x80 | (c & 0x3f)) << 16 | (0x80 | (c >> 6 & 0x3f)) << 8 | (0xe0 | c >> 12)
+    }
+}
+
+const fn utf8_table() -> [[u32; 64]; 16] {
+    let mut table = [[0u32; 64]; 16];
+    let mut p = 0;
+    while p < PAGES.len() {
+        // Code 0 is the padding, which decodes to '\0' on every page.
+        table[p][0] = utf8_entry(0);
+        let mut code = 1;
+        while code < 64 {
+            table[p][code] = utf8_entry(PAGES[p][code] as u32);
+            code += 1;
+        }
+        p += 1;
+    }
+    table
+}
+
+static UTF8: [[u32; 64]; 16] = utf8_table();
+
+fn decode_with_table<N: PackedValue>(n: N, out: &mut [u8]) -> usize {
+    let mut it = n.decode_sixbit();
+    let bits = code_bits(it.tag);
+    let chinese = it.tag == CHINESE_4BIT_TAG;
+    let table = &UTF8[it.tag];
+    let mut at = 0;
+    while it.len != 0 {
+        let code = it.peek_code();
+        let e = if chinese && code != 0 {
+            utf8_entry(CHINESE_LO as u32 + code as u32 - 1)
+        } else {
+            table[code]
+        };
+        let b = e.to_le_bytes();
+        // All 4 bytes when there's room, rather than a copy of variable
+        // length, which is a call to memcpy.
+        if out.len() >= at + 4 {
+            out[at..at + 4].copy_from_slice(&b);
+        } else {
+            out[at..at + b[3] as usize].copy_from_slice(&b[..b[3] as usize]);
+        }
+        at += b[3] as usize;
+        it.tmp <<= bits;
+        it.len -= 1;
+    }
+    at
+}
+
+pub(crate) fn decode_utf8_into<N: PackedValue>(n: N, out: &mut [u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if crate::tag_of(n) == 0 && N::NBITS >= 64 && x86::has_ssse3() {
+            let len = n.char_len();
+            let mut tmp = n;
+            tmp <<= N::NTAGBITS;
+            let mut at = 0;
+            while at < len {
+                // The next 16 codes, in 12 bytes.
+                let (mut hi, mut lo) = (0u64, 0u32);
+                for _ in 0..8 {
+                    hi = hi << 8 | tmp.most_significant_byte() as u64;
+                    tmp <<= 8;
+                }
+                for _ in 0..4 {
+                    lo = lo << 8 | tmp.most_significant_byte() as u32;
+                    tmp <<= 8;
+                }
+                // Safe because the CPU has SSSE3.
+                let ascii = unsafe { x86::latin_ascii_ssse3(hi, lo) };
+                let count = min(16, len - at);
+                if out.len() >= at + 16 {
+                    out[at..at + 16].copy_from_slice(&ascii);
+                } else {
+                    out[at..at + count].copy_from_slice(&ascii[..count]);
+                }
+                at += count;
+            }
+            return len;
+        }
+    }
+    decode_with_table(n, out)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    // Without std there's no runtime detection, so SSSE3 is only used when
+    // the whole build targets it.
+    pub(super) fn has_ssse3() -> bool {
+        #[cfg(feature = "std")]
+        {
+            is_x86_feature_detected!("ssse3")
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            cfg!(target_feature = "ssse3")
+        }
+    }
+
+    // Expand 16 Latin codes, packed into 12 bytes with the first highest, to
+    // 16 ASCII bytes.
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn latin_ascii_ssse3(hi: u64, lo: u32) -> [u8; 16] {
+        // The 12 bytes in memory order: first byte lowest.
+        let v = _mm_set_epi64x(lo.swap_bytes() as i64, hi.swap_bytes() as i64);
+        // Bytes b0 b1 b2 hold codes a b c d; each lane gets b1 b0 b2 b1,
+        // so that a and c are in its low 16 bits and b and d in its high.
+        let v = _mm_shuffle_epi8(
+            v,
+            _mm_set_epi8(10, 11, 9, 10, 7, 8, 6, 7, 4, 5, 3, 4, 1, 2, 0, 1),
+        );
+        // a and c down to the bottom of their bytes, b and d up to it.
+        let ac = _mm_mulhi_epu16(
+            _mm_and_si128(v, _mm_set1_epi32(0x0fc0_fc00)),
+            _mm_set1_epi32(0x0400_0040),
+        );
+        let bd = _mm_mullo_epi16(
+            _mm_and_si128(v, _mm_set1_epi32(0x003f_03f0)),
+            _mm_set1_epi32(0x0100_0010),
+        );
+        let codes = _mm_or_si128(ac, bd);
+        // '0' is code 1, 'A' 11, '_' 37 and 'a' 38, and code 0 is '\0'.
+        let digit = _mm_set1_epi8((b'0' - 1) as i8);
+        let upper = _mm_and_si128(
+            _mm_cmpgt_epi8(codes, _mm_set1_epi8(10)),
+            _mm_set1_epi8((b'A' - b'9' - 1) as i8),
+        );
+        let under = _mm_and_si128(
+            _mm_cmpgt_epi8(codes, _mm_set1_epi8(36)),
+            _mm_set1_epi8((b'_' - b'Z' - 1) as i8),
+        );
+        let lower = _mm_and_si128(_mm_cmpgt_epi8(codes, _mm_set1_epi8(37)), _mm_set1_epi8(1));
+        let offset = _mm_add_epi8(_mm_add_epi8(digit, upper), _mm_add_epi8(under, lower));
+        let zero = _mm_cmpeq_epi8(codes, _mm_setzero_si128());
+        let mut ascii = [0u8; 16];
+        let out = _mm_add_epi8(codes, _mm_andnot_si128(zero, offset));
+        _mm_storeu_si128(ascii.as_mut_ptr() as *mut __m128i, out);
+        ascii
+    }
+}
+
+// A packed value decoded into a buffer on the stack, to use as a `&str`
+// without allocating. 84 bytes holds anything up to 128 bits wide, and most
+// strings in the wider values. Decoding leaves scratch bytes past the end of
+// the string, so everything but Clone looks only at the string.
+#[derive(Clone, Copy)]
+pub struct SixbitStr {
+    len: u8,
+    buf: [u8; SixbitStr::CAPACITY],
+}
+
+impl SixbitStr {
+    pub const CAPACITY: usize = 84;
+
+    // None if the string takes more than CAPACITY bytes.
+    pub fn new<N: PackedValue>(n: N) -> Option<SixbitStr> {
+        if 3 * N::NCHARS > SixbitStr::CAPACITY
+            && n.decode_sixbit().map(char::len_utf8).sum::<usize>() > SixbitStr::CAPACITY
+        {
+            return None;
+        }
+        let mut s = SixbitStr {
+            len: 0,
+            buf: [0; SixbitStr::CAPACITY],
+        };
+        s.len = decode_utf8_into(n, &mut s.buf) as u8;
+        Some(s)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Safe because the buffer was filled from whole chars.
+        unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len as usize]) }
+    }
+}
+
+macro_rules! impl_from_narrow {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for SixbitStr {
+                fn from(n: $t) -> SixbitStr {
+                    // At most 3 bytes for each of at most 21 characters.
+                    SixbitStr::new(n).unwrap()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_narrow!(u8, u16, u32, u64, u128);
+
+impl Deref for SixbitStr {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SixbitStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for SixbitStr {
+    fn eq(&self, other: &SixbitStr) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SixbitStr {}
+
+impl PartialOrd for SixbitStr {
+    fn partial_cmp(&self, other: &SixbitStr) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SixbitStr {
+    fn cmp(&self, other: &SixbitStr) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for SixbitStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for SixbitStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for SixbitStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Wide, U192, U256};
+    use rand::{Rng, SeedableRng};
+
+    fn check<N: PackedValue>(s: &[u8]) {
+        let expect = match core::str::from_utf8(s) {
+            Ok(s) => encode::<N, _>(s.chars()),
+            Err(e) => Err(EncodeError::InvalidUtf8 {
+                position: core::str::from_utf8(&s[..e.valid_up_to()])
+                    .unwrap()
+                    .chars()
+                    .count(),
+            }),
+        };
+        assert_eq!(encode_utf8::<N>(s), expect, "{:?}", s);
+    }
+
+    fn check_all(s: &[u8]) {
+        check::<u8>(s);
+        check::<u16>(s);
+        check::<u32>(s);
+        check::<u64>(s);
+        check::<u128>(s);
+        check::<U192>(s);
+        check::<U256>(s);
+    }
+
+    #[test]
+    fn same_as_encode() {
+        for s in [
+            "",
+            "a",
+            "09AZ_az",
+            "NO_CARRIER",
+            "PRINTER_is_on_FIRE",
+            "PRINTER_is_on_FIRE_ok",
+            "PRINTER_FULLY_OPERATIONAL",
+            "sh@rk",
+            "a b",
+            "a\0",
+            "/:@[`{",
+            "αλήθεια",
+            "合伙人",
+            "ｲｸﾂｶﾉ",
+            "abcé",
+        ]
+        .iter()
+        {
+            check_all(s.as_bytes());
+        }
+    }
+
+    // Every byte value at every position of an otherwise-Latin string, which
+    // covers each class boundary in each byte of a chunk.
+    #[test]
+    fn every_byte() {
+        let base = *b"abcdefghijklmnopqrst";
+        for len in [1, 7, 8, 9, 10, 20].iter().cloned() {
+            for i in 0..len {
+                for b in 0..=255u8 {
+                    let mut s = base;
+                    s[i] = b;
+                    check::<u64>(&s[..len]);
+                    check::<u128>(&s[..len]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random() {
+        const LATIN: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ_abcdefghijklmnopqrstuvwxyz";
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x5157b17);
+        for _ in 0..10000 {
+            let len = rng.gen_range(0..=44);
+            let s: Vec<u8> = (0..len)
+                .map(|_| LATIN[rng.gen_range(0..LATIN.len())])
+                .collect();
+            check_all(&s);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            encode_utf8::<u64>(b"ab\xffc"),
+            Err(EncodeError::InvalidUtf8 { position: 2 })
+        );
+        assert_eq!(
+            encode_utf8::<u64>("αβ".as_bytes().split_last().unwrap().1),
+            Err(EncodeError::InvalidUtf8 { position: 1 })
+        );
+        assert_eq!(
+            encode_utf8::<u64>(b"\x80").unwrap_err().to_string(),
+            "invalid UTF-8 at position 0"
+        );
+    }
+
+    // Sequences cut short or with bad continuations, overlong forms,
+    // surrogates and values past U+10FFFF, after a prefix that encodes and
+    // after one that doesn't.
+    #[test]
+    fn malformed() {
+        const CONT: [u8; 8] = [0x41, 0x7f, 0x80, 0x8f, 0x90, 0x9f, 0xa0, 0xbf];
+        for prefix in ["", "a", "αβ", "a@"].iter() {
+            for lead in 0x80..=0xffu8 {
+                for &y in CONT.iter() {
+                    for &z in CONT.iter() {
+                        let mut s = prefix.as_bytes().to_vec();
+                        s.extend_from_slice(&[lead, y, z, 0x80]);
+                        for len in prefix.len() + 1..=s.len() {
+                            check::<u64>(&s[..len]);
+                            check::<u128>(&s[..len]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_decode<N: PackedValue>(n: N) {
+        let expect: String = n.decode_sixbit().collect();
+        let mut out = vec![0xaa; 3 * N::NCHARS];
+        let len = n.decode_utf8_into(&mut out);
+        assert_eq!(&out[..len], expect.as_bytes(), "{:x}", n);
+        let mut exact = vec![0; expect.len()];
+        assert_eq!(n.decode_utf8_into(&mut exact), len);
+        assert_eq!(exact, expect.as_bytes());
+        match SixbitStr::new(n) {
+            Some(s) => assert_eq!(&*s, expect),
+            None => assert!(expect.len() > SixbitStr::CAPACITY),
+        }
+    }
+
+    // Random bits at every width, so every page, reserved tags and codes
+    // that no string would produce, and zero codes in the middle of Latin
+    // strings for the shuffle path.
+    #[test]
+    fn decode_random() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0xdec0de);
+        for _ in 0..20000 {
+            let limbs: [u64; 5] = rng.gen();
+            let tag = rng.gen_range(0..16u64) << 60;
+            let latin = limbs[0] >> 4;
+            check_decode(limbs[0] as u8);
+            check_decode(limbs[0] as u16);
+            check_decode(limbs[0] as u32);
+            check_decode(limbs[0]);
+            check_decode(latin);
+            check_decode(limbs[0] >> 4 | tag);
+            check_decode((limbs[0] as u128) << 64 | limbs[1] as u128);
+            check_decode((latin as u128) << 64 | limbs[1] as u128);
+            check_decode(Wide([limbs[0], limbs[1], limbs[2]]));
+            check_decode(Wide([latin, limbs[1], limbs[2]]));
+            check_decode(Wide([latin, limbs[1], limbs[2], limbs[3]]));
+            check_decode(Wide([limbs[0] >> 4 | tag, limbs[1], limbs[2], limbs[3]]));
+            check_decode(Wide(limbs));
+        }
+    }
+
+    #[test]
+    fn decode_strings() {
+        for s in [
+            "",
+            "a",
+            "09AZ_az",
+            "PRINTER_is_on_FIRE_and_the_fax_is_too_hot",
+            "αλήθεια",
+            "合伙人",
+            "ｲｸﾂｶﾉ",
+            "किंकर्तव्यविमूढ़",
+        ]
+        .iter()
+        {
+            if let Ok(n) = encode::<U256, _>(s.chars()) {
+                check_decode(n);
+            }
+            if let Ok(n) = encode::<u64, _>(s.chars()) {
+                check_decode(n);
+                assert_eq!(SixbitStr::from(n).as_str(), *s);
+            }
+        }
+        let s = SixbitStr::from(encode::<u64, _>("ｲｸﾂｶﾉ".chars()).unwrap());
+        assert_eq!(s.len(), 15);
+        assert_eq!(format!("{} {:?}", s, s), "ｲｸﾂｶﾉ \"ｲｸﾂｶﾉ\"");
+        let long: U256 = encode("किंकर्तव्यविमूढ़किंकर्तव्यविमूढ़".chars()).unwrap();
+        assert_eq!(SixbitStr::new(long), None);
+    }
+
+    fn hash_of<T: Hash + ?Sized>(t: &T) -> u64 {
+        let mut h = std::collections::hash_map::DefaultHasher::new();
+        t.hash(&mut h);
+        h.finish()
+    }
+
+    // Scratch bytes past the end differ with the width and the decoding path,
+    // and mustn't show.
+    #[test]
+    fn str_equality() {
+        for w in ["", "abc", "NO_CA", "x_9"].iter() {
+            let strs = [
+                SixbitStr::from(encode::<u32, _>(w.chars()).unwrap()),
+                SixbitStr::from(encode::<u64, _>(w.chars()).unwrap()),
+                SixbitStr::from(encode::<u128, _>(w.chars()).unwrap()),
+            ];
+            for s in strs.iter() {
+                assert_eq!(s.as_str(), *w);
+                assert_eq!(*s, strs[0]);
+                assert_eq!(s.cmp(&strs[0]), Ordering::Equal);
+                assert_eq!(hash_of(s), hash_of(&strs[0]));
+                assert_eq!(hash_of(s), hash_of(*w));
+            }
+        }
+        let abc = SixbitStr::from(encode::<u64, _>("abc".chars()).unwrap());
+        let abd = SixbitStr::from(encode::<u128, _>("abd".chars()).unwrap());
+        assert!(abc < abd);
+    }
+}
@@ -0,0 +1,253 @@
+use core::fmt;
+use core::ops::{BitAnd, BitOrAssign, BitXor, Not, ShlAssign};
+
+use crate::PackedValue;
+
+// Packed values wider than u128, made of `L` u64 limbs with the most
+// significant first, so that the derived (lexicographic) ordering on the
+// array is the ordering on the whole value.
+//
+// The tag follows the same rule as the integer widths: whatever is left over
+// above the 6-bit codes, which is 2 or 4 bits depending on the width mod 6.
+// The one new case is a width that's a multiple of 6, like 192 bits: that
+// would leave no room for a tag at all, so one code's worth of bits is given
+// up for a 4-bit tag and the other 2 are padding below the last code, always
+// zero.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Wide<const L: usize>(pub [u64; L]);
+
+pub type U192 = Wide<3>;
+pub type U256 = Wide<4>;
+
+impl<const L: usize> Default for Wide<L> {
+    fn default() -> Wide<L> {
+        Wide([0; L])
+    }
+}
+
+impl<const L: usize> ShlAssign<usize> for Wide<L> {
+    // Unlike the integers, shifting by the whole width or more is fine: it
+    // leaves zero.
+    fn shl_assign(&mut self, s: usize) {
+        let (limbs, bits) = (s / 64, s % 64);
+        for i in 0..L {
+            let j = i + limbs;
+            let hi = if j < L { self.0[j] << bits } else { 0 };
+            let lo = if bits != 0 && j + 1 < L {
+                self.0[j + 1] >> (64 - bits)
+            } else {
+                0
+            };
+            self.0[i] = hi | lo;
+        }
+    }
+}
+
+impl<const L: usize> BitOrAssign for Wide<L> {
+    fn bitor_assign(&mut self, other: Wide<L>) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a |= b;
+        }
+    }
+}
+
+impl<const L: usize> BitAnd for Wide<L> {
+    type Output = Wide<L>;
+    fn bitand(mut self, other: Wide<L>) -> Wide<L> {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a &= b;
+        }
+        self
+    }
+}
+
+impl<const L: usize> BitXor for Wide<L> {
+    type Output = Wide<L>;
+    fn bitxor(mut self, other: Wide<L>) -> Wide<L> {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a ^= b;
+        }
+        self
+    }
+}
+
+impl<const L: usize> Not for Wide<L> {
+    type Output = Wide<L>;
+    fn not(mut self) -> Wide<L> {
+        for a in self.0.iter_mut() {
+            *a = !*a;
+        }
+        self
+    }
+}
+
+impl<const L: usize> fmt::LowerHex for Wide<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        for limb in self.0.iter() {
+            write!(f, "{:016x}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const L: usize> PackedValue for Wide<L> {
+    const NTAGBITS: usize = if (64 * L) % 6 == 2 { 2 } else { 4 };
+    const NCHARS: usize = (64 * L - Self::NTAGBITS) / 6;
+
+    fn truncating_cast_from(i: usize) -> Wide<L> {
+        let mut w = Wide([0; L]);
+        w.0[L - 1] = i as u64;
+        w
+    }
+    fn most_significant_byte(self) -> u8 {
+        (self.0[0] >> 56) as u8
+    }
+    fn leading_zeros(self) -> u32 {
+        match self.0.iter().position(|&x| x != 0) {
+            Some(i) => i as u32 * 64 + self.0[i].leading_zeros(),
+            None => L as u32 * 64,
+        }
+    }
+    fn trailing_zeros(self) -> u32 {
+        match self.0.iter().rposition(|&x| x != 0) {
+            Some(i) => (L - 1 - i) as u32 * 64 + self.0[i].trailing_zeros(),
+            None => L as u32 * 64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode, validate, DecodeSixbit, EncodeError, Page};
+
+    fn round_trip<N: PackedValue>(s: &str) -> Result<N, EncodeError> {
+        let n: N = encode(s.chars())?;
+        assert_eq!(validate(n), Ok(()));
+        assert_eq!(n.decode_sixbit().collect::<String>(), s);
+        assert_eq!(
+            n.decode_sixbit().rev().collect::<String>(),
+            s.chars().rev().collect::<String>()
+        );
+        Ok(n)
+    }
+
+    #[test]
+    fn layout() {
+        assert_eq!(
+            (U192::NTAGBITS, U192::NCHARS, U192::NWIDECHARS),
+            (4, 31, 12)
+        );
+        assert_eq!(U192::NPADBITS, 2);
+        assert_eq!(
+            (U256::NTAGBITS, U256::NCHARS, U256::NWIDECHARS),
+            (4, 42, 16)
+        );
+        assert_eq!(U256::NPADBITS, 0);
+        assert_eq!((Wide::<5>::NTAGBITS, Wide::<5>::NCHARS), (2, 53));
+
+        // One limb is laid out exactly like a u64, and two like a u128.
+        for s in ["", "NO_CARRIER", "αλήθεια", "合伙人"].iter() {
+            let w: Wide<1> = encode(s.chars()).unwrap();
+            assert_eq!(w.0[0], encode::<u64, _>(s.chars()).unwrap());
+        }
+        let w: Wide<2> = encode("PRINTER_is_on_FIRE".chars()).unwrap();
+        let n: u128 = encode("PRINTER_is_on_FIRE".chars()).unwrap();
+        assert_eq!(w.0, [(n >> 64) as u64, n as u64]);
+        assert_eq!(format!("{:x}", w), format!("{:032x}", n));
+    }
+
+    #[test]
+    fn shifts() {
+        let one = U256::truncating_cast_from(1);
+        for s in 0..256 {
+            let mut w = one;
+            w <<= s;
+            assert_eq!(w.trailing_zeros(), s as u32);
+            assert_eq!(w.leading_zeros(), 255 - s as u32);
+            assert_eq!(w.0[3 - s / 64], 1 << (s % 64));
+        }
+        let mut w = !U256::default();
+        w <<= 256;
+        assert_eq!(w, U256::default());
+        assert_eq!(w.leading_zeros(), 256);
+    }
+
+    #[test]
+    fn strings() {
+        let long = "PRINTER_is_on_FIRE_and_the_fax_is_too";
+        assert!(round_trip::<U192>("PRINTER_is_on_FIRE_and_the_fax").is_ok());
+        assert!(round_trip::<U192>("PRINTER_is_on_FIRE_and_the_fax_").is_ok());
+        assert_eq!(
+            round_trip::<U192>(long),
+            Err(EncodeError::TooLong {
+                position: 31,
+                page: Page::Latin,
+                needed: 37,
+                available: 31,
+                fits_in: Some(256),
+            })
+        );
+        assert!(round_trip::<U256>(long).is_ok());
+        assert!(round_trip::<U192>("καλημέρα").is_ok());
+        assert!(round_trip::<U256>("किंकर्तव्यविमूढ़").is_ok());
+        assert!(round_trip::<U192>("高速火车站再见一高速火车").is_ok());
+        assert_eq!(
+            round_trip::<U192>("高速火车站再见一高速火车站"),
+            Err(EncodeError::TooLong {
+                position: 12,
+                page: Page::Chinese,
+                needed: 13,
+                available: 12,
+                fits_in: Some(256),
+            })
+        );
+        assert!(round_trip::<Wide<5>>("محافظت").is_ok());
+        assert_eq!(
+            round_trip::<Wide<5>>("ΨΩ"),
+            Err(EncodeError::PageUnavailable {
+                position: 0,
+                page: Page::Greek,
+            })
+        );
+
+        let full: U192 = encode("PRINTER_is_on_FIRE_and_the_fax_".chars()).unwrap();
+        assert_eq!(full.char_len(), 31);
+        assert_eq!(full.0[2] & 0b11, 0);
+        let wide: U192 = encode("高速火车站再见一高速火车".chars()).unwrap();
+        assert_eq!(wide.char_len(), 12);
+        let mut junk = full;
+        junk |= U192::truncating_cast_from(1);
+        assert!(validate(junk).is_err());
+    }
+
+    #[test]
+    fn ordering() {
+        let words = [
+            "",
+            "A",
+            "PRINTER_is_on_FIRE_and_the_fax",
+            "PRINTER_is_on_FIRE_and_the_fax_",
+            "a",
+            "αβγ",
+            "абв",
+            "一",
+            "一丁",
+            "ｦｧｨ",
+        ];
+        let packed: Vec<U192> = words.iter().map(|w| encode(w.chars()).unwrap()).collect();
+        for pair in packed.windows(2) {
+            assert!(pair[0] < pair[1]);
+        }
+        let (a, b): (U256, U256) = (
+            encode("Printer".chars()).unwrap(),
+            encode("Printer_Working".chars()).unwrap(),
+        );
+        assert!(a.is_prefix_of(b));
+        assert_eq!(a.common_prefix_len(b), 7);
+        assert_eq!(a.to_uppercase(), encode("PRINTER".chars()).ok());
+    }
+}